@@ -3,8 +3,8 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use ipr::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
-use ipr::state::State;
+use ipr::msg::{Cw20HookMsg, ExecuteMsg, InstantiateMsg, QueryMsg, RewardContractMsg};
+use ipr::state::{Campaign, Config};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -15,5 +15,8 @@ fn main() {
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
-    export_schema(&schema_for!(State), &out_dir);
+    export_schema(&schema_for!(Cw20HookMsg), &out_dir);
+    export_schema(&schema_for!(RewardContractMsg), &out_dir);
+    export_schema(&schema_for!(Config), &out_dir);
+    export_schema(&schema_for!(Campaign), &out_dir);
 }
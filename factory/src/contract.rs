@@ -0,0 +1,64 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult};
+use cw2::set_contract_version;
+use crate::error::ContractError;
+use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use crate::state::{Config, CONFIG};
+use crate::handler::execute as ExecuteHandler;
+use crate::handler::query as QueryHandler;
+use crate::handler::reply as ReplyHandler;
+
+// version info for migration info
+const CONTRACT_NAME: &str = "crates.io:ipr-factory";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    let config = Config {
+        owner: info.sender,
+        ipr_code_id: msg.ipr_code_id,
+        raise_count: 0,
+    };
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    CONFIG.save(deps.storage, &config)?;
+    Ok(Response::new()
+        .add_attribute("method", "instantiate")
+        .add_attribute("owner", config.owner)
+        .add_attribute("ipr_code_id", config.ipr_code_id.to_string()))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::CreateRaise { name, template } => ExecuteHandler::try_create_raise(deps, info, env, name, *template),
+        ExecuteMsg::UpdateConfig { owner, ipr_code_id } => ExecuteHandler::try_update_config(deps, info, owner, ipr_code_id),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        ExecuteHandler::INSTANTIATE_REPLY_ID => ReplyHandler::reply_instantiate(deps, msg),
+        id => Err(ContractError::UnknownReplyId { id }),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::GetConfig {} => to_binary(&QueryHandler::query_config(deps)?),
+        QueryMsg::GetRaise { id } => to_binary(&QueryHandler::query_raise(deps, id)?),
+        QueryMsg::ListRaises { start_after, limit } => to_binary(&QueryHandler::query_raises(deps, start_after, limit)?),
+    }
+}
@@ -0,0 +1,23 @@
+use cosmwasm_std::StdError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("Unknown reply id {id}")]
+    UnknownReplyId { id: u64 },
+
+    #[error("Invalid raise template: {0}")]
+    InvalidTemplate(ipr::ContractError),
+
+    #[error("A raise name has to be between 1 and {max} characters")]
+    InvalidRaiseName { max: usize },
+
+    #[error("The instantiated raise contract did not report its address")]
+    MissingRaiseAddress {},
+}
@@ -0,0 +1,86 @@
+use cosmwasm_std::{to_binary, DepsMut, Env, Event, MessageInfo, Response, SubMsg, WasmMsg};
+use ipr::msg::InstantiateMsg as RaiseInstantiateMsg;
+
+use crate::ContractError;
+use crate::state::{PendingRaise, CONFIG, PENDING_RAISE};
+
+pub const INSTANTIATE_REPLY_ID: u64 = 1;
+
+const MAX_NAME_LENGTH: usize = 64;
+
+// vets the template the way the raise contract will, then instantiates it and registers
+// the new contract once the reply reports its address
+pub fn try_create_raise(deps: DepsMut, info: MessageInfo, env: Env, name: String, mut template: RaiseInstantiateMsg) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        return Err(ContractError::InvalidRaiseName { max: MAX_NAME_LENGTH });
+    }
+    // pinned here so the registered dates are the ones the raise runs on
+    let now = env.block.time.seconds();
+    template.start_time = Some(template.start_time.unwrap_or(now));
+    let owner = match &template.owner {
+        Some(owner) => deps.api.addr_validate(owner)?,
+        None => config.owner.clone(),
+    };
+    template.owner = Some(owner.to_string());
+    template.validate().map_err(ContractError::InvalidTemplate)?;
+    deps.api.addr_validate(template.treasury_wallet.as_str())?;
+    deps.api.addr_validate(template.reward_contract.as_str())?;
+
+    let pending = PendingRaise {
+        name,
+        code_id: config.ipr_code_id,
+        owner,
+        reward_token: deps.api.addr_validate(&template.reward_token)?,
+        start_time: template.start_time.unwrap_or(now),
+        end_time: template.end_time,
+        created_at: now,
+    };
+    PENDING_RAISE.save(deps.storage, &pending)?;
+    Ok(Response::new()
+        .add_attribute("method", "try_create_raise")
+        .add_submessage(SubMsg::reply_on_success(
+            WasmMsg::Instantiate {
+                admin: Some(config.owner.to_string()),
+                code_id: config.ipr_code_id,
+                msg: to_binary(&template)?,
+                funds: vec![],
+                label: format!("ipr raise {}: {}", config.raise_count, pending.name),
+            },
+            INSTANTIATE_REPLY_ID,
+        ))
+        .add_event(Event::new("ipr_factory_raise_requested")
+            .add_attribute("raise_id", config.raise_count.to_string())
+            .add_attribute("name", pending.name)
+            .add_attribute("code_id", pending.code_id.to_string())
+            .add_attribute("owner", pending.owner.to_string())))
+}
+
+pub fn try_update_config(deps: DepsMut, info: MessageInfo, owner: Option<String>, ipr_code_id: Option<u64>) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    let old_config = config.clone();
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    if let Some(owner) = owner {
+        config.owner = deps.api.addr_validate(&owner)?;
+    }
+    if let Some(ipr_code_id) = ipr_code_id {
+        config.ipr_code_id = ipr_code_id;
+    }
+    CONFIG.save(deps.storage, &config)?;
+    Ok(Response::new()
+        .add_attribute("method", "try_update_config")
+        .add_event(config_changed_event("owner", old_config.owner, &config.owner))
+        .add_event(config_changed_event("ipr_code_id", old_config.ipr_code_id, config.ipr_code_id)))
+}
+
+pub fn config_changed_event(key: &str, old_value: impl ToString, new_value: impl ToString) -> Event {
+    Event::new("ipr_factory_config_changed")
+        .add_attribute("key", key)
+        .add_attribute("old_value", old_value.to_string())
+        .add_attribute("new_value", new_value.to_string())
+}
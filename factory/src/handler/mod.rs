@@ -0,0 +1,3 @@
+pub mod execute;
+pub mod query;
+pub mod reply;
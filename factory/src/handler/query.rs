@@ -0,0 +1,46 @@
+use cosmwasm_std::{Deps, Order, StdResult};
+use cw_storage_plus::{Bound, U64Key};
+
+use crate::msg::{ConfigResponse, RaiseResponse, RaisesResponse};
+use crate::state::{Raise, CONFIG, RAISES};
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    Ok(ConfigResponse {
+        owner: config.owner,
+        ipr_code_id: config.ipr_code_id,
+        raise_count: config.raise_count,
+    })
+}
+
+pub fn query_raise(deps: Deps, id: u64) -> StdResult<RaiseResponse> {
+    Ok(raise_response(RAISES.load(deps.storage, U64Key::new(id))?))
+}
+
+pub fn query_raises(deps: Deps, start_after: Option<u64>, limit: Option<u32>) -> StdResult<RaisesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|id| Bound::exclusive(U64Key::new(id)));
+    let raises: StdResult<Vec<RaiseResponse>> = RAISES
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| Ok(raise_response(item?.1)))
+        .collect();
+    Ok(RaisesResponse { raises: raises? })
+}
+
+fn raise_response(raise: Raise) -> RaiseResponse {
+    RaiseResponse {
+        id: raise.id,
+        name: raise.name,
+        address: raise.address,
+        code_id: raise.code_id,
+        owner: raise.owner,
+        reward_token: raise.reward_token,
+        start_time: raise.start_time,
+        end_time: raise.end_time,
+        created_at: raise.created_at,
+    }
+}
@@ -0,0 +1,41 @@
+use cosmwasm_std::{DepsMut, Event, Reply, Response, StdError};
+use cw_storage_plus::U64Key;
+
+use crate::ContractError;
+use crate::state::{Raise, CONFIG, PENDING_RAISE, RAISES};
+
+pub fn reply_instantiate(deps: DepsMut, msg: Reply) -> Result<Response, ContractError> {
+    // the raise contract is up, so it can be registered under the next id
+    let events = msg.result.into_result().map_err(StdError::generic_err)?.events;
+    let address = events
+        .iter()
+        .filter(|event| event.ty == "instantiate_contract")
+        .flat_map(|event| event.attributes.iter())
+        .find(|attribute| attribute.key == "contract_address")
+        .map(|attribute| attribute.value.clone())
+        .ok_or(ContractError::MissingRaiseAddress {})?;
+    let pending = PENDING_RAISE.load(deps.storage)?;
+    let mut config = CONFIG.load(deps.storage)?;
+    let raise = Raise {
+        id: config.raise_count,
+        name: pending.name,
+        address: deps.api.addr_validate(&address)?,
+        code_id: pending.code_id,
+        owner: pending.owner,
+        reward_token: pending.reward_token,
+        start_time: pending.start_time,
+        end_time: pending.end_time,
+        created_at: pending.created_at,
+    };
+    RAISES.save(deps.storage, U64Key::new(raise.id), &raise)?;
+    config.raise_count += 1;
+    CONFIG.save(deps.storage, &config)?;
+    PENDING_RAISE.remove(deps.storage);
+    Ok(Response::new()
+        .add_attribute("method", "reply_instantiate")
+        .add_event(Event::new("ipr_factory_raise_created")
+            .add_attribute("raise_id", raise.id.to_string())
+            .add_attribute("name", raise.name)
+            .add_attribute("address", raise.address.to_string())
+            .add_attribute("owner", raise.owner.to_string())))
+}
@@ -0,0 +1,50 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use cosmwasm_std::Addr;
+use ipr::msg::InstantiateMsg as RaiseInstantiateMsg;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InstantiateMsg {
+    pub ipr_code_id: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+    // instantiates a raise contract from the template, the template's owner runs it
+    CreateRaise { name: String, template: Box<RaiseInstantiateMsg> },
+    UpdateConfig { owner: Option<String>, ipr_code_id: Option<u64> },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    GetConfig {},
+    GetRaise { id: u64 },
+    ListRaises { start_after: Option<u64>, limit: Option<u32> },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigResponse {
+    pub owner: Addr,
+    pub ipr_code_id: u64,
+    pub raise_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RaiseResponse {
+    pub id: u64,
+    pub name: String,
+    pub address: Addr,
+    pub code_id: u64,
+    pub owner: Addr,
+    pub reward_token: Addr,
+    pub start_time: u64,
+    pub end_time: Option<u64>,
+    pub created_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RaisesResponse {
+    pub raises: Vec<RaiseResponse>,
+}
@@ -0,0 +1,44 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use cosmwasm_std::Addr;
+use cw_storage_plus::{Item, Map, U64Key};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Config {
+    pub owner: Addr,
+    /// code new raise contracts are instantiated from
+    pub ipr_code_id: u64,
+    pub raise_count: u64,
+}
+
+// a raise contract launched by this factory and what it was launched for
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Raise {
+    pub id: u64,
+    pub name: String,
+    pub address: Addr,
+    pub code_id: u64,
+    pub owner: Addr,
+    pub reward_token: Addr,
+    /// deposits are taken from this time on
+    pub start_time: u64,
+    /// deposits are no longer taken from this time on, open-ended when unset
+    pub end_time: Option<u64>,
+    pub created_at: u64,
+}
+
+// a raise waiting on the reply that reports its contract address
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingRaise {
+    pub name: String,
+    pub code_id: u64,
+    pub owner: Addr,
+    pub reward_token: Addr,
+    pub start_time: u64,
+    pub end_time: Option<u64>,
+    pub created_at: u64,
+}
+
+pub const CONFIG: Item<Config> = Item::new("config");
+pub const PENDING_RAISE: Item<PendingRaise> = Item::new("pending_raise");
+pub const RAISES: Map<U64Key, Raise> = Map::new("raises");
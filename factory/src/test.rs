@@ -0,0 +1,141 @@
+use cosmwasm_bignumber::Decimal256;
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage};
+use cosmwasm_std::{from_binary, Addr, ContractResult, CosmosMsg, Event, OwnedDeps, Reply, Response, SubMsgExecutionResponse, Timestamp, Uint128, WasmMsg};
+use ipr::msg::InstantiateMsg as RaiseInstantiateMsg;
+use ipr::state::RewardMode;
+
+use crate::contract::{execute, instantiate, query, reply};
+use crate::handler::execute::INSTANTIATE_REPLY_ID;
+use crate::msg::{ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg, RaiseResponse, RaisesResponse};
+use crate::ContractError;
+
+const OWNER: &str = "owner";
+const CODE_ID: u64 = 7;
+const NOW: u64 = 1_000_000;
+
+type Deps = OwnedDeps<MockStorage, MockApi, MockQuerier>;
+
+fn template() -> RaiseInstantiateMsg {
+    RaiseInstantiateMsg {
+        owner: None,
+        treasury_wallet: Addr::unchecked("treasury"),
+        reward_contract: Addr::unchecked("reward_contract"),
+        reward_token: "min_token".to_string(),
+        reward_mode: RewardMode::Mint,
+        ust_deposited: Uint128::zero(),
+        sellback_price: Decimal256::percent(50),
+        anc_market: "anchor_market".to_string(),
+        aust_contract: "aust_token".to_string(),
+        tier0rate: Decimal256::permille(1),
+        tier0time: 0,
+        tier1rate: Decimal256::permille(2),
+        tier1time: 1_000,
+        tier2rate: Decimal256::permille(3),
+        tier2time: 2_000,
+        tier3rate: Decimal256::permille(4),
+        tier3time: 3_000,
+        start_time: None,
+        end_time: None,
+        deposit_cap: None,
+        soft_cap: None,
+        deadline: None,
+        yield_split: None,
+    }
+}
+
+fn setup() -> Deps {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), InstantiateMsg { ipr_code_id: CODE_ID }).unwrap();
+    deps
+}
+
+fn create_raise(deps: &mut Deps, sender: &str, name: &str, template: RaiseInstantiateMsg) -> Result<Response, ContractError> {
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(NOW);
+    let msg = ExecuteMsg::CreateRaise { name: name.to_string(), template: Box::new(template) };
+    execute(deps.as_mut(), env, mock_info(sender, &[]), msg)
+}
+
+// the reply the chain sends once the raise contract is instantiated at `address`
+fn instantiated(deps: &mut Deps, address: Option<&str>) -> Result<Response, ContractError> {
+    let events = address
+        .map(|address| vec![Event::new("instantiate_contract")
+            .add_attribute("code_id", CODE_ID.to_string())
+            .add_attribute("contract_address", address)])
+        .unwrap_or_default();
+    let msg = Reply {
+        id: INSTANTIATE_REPLY_ID,
+        result: ContractResult::Ok(SubMsgExecutionResponse { events, data: None }),
+    };
+    reply(deps.as_mut(), mock_env(), msg)
+}
+
+fn launch(deps: &mut Deps, name: &str, address: &str) {
+    create_raise(deps, OWNER, name, template()).unwrap();
+    instantiated(deps, Some(address)).unwrap();
+}
+
+fn list_raises(deps: &Deps, start_after: Option<u64>, limit: Option<u32>) -> Vec<RaiseResponse> {
+    let raises: RaisesResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::ListRaises { start_after, limit }).unwrap()).unwrap();
+    raises.raises
+}
+
+#[test]
+fn instantiate_sets_the_sender_as_owner() {
+    let deps = setup();
+    let config: ConfigResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {}).unwrap()).unwrap();
+    assert_eq!(config, ConfigResponse { owner: Addr::unchecked(OWNER), ipr_code_id: CODE_ID, raise_count: 0 });
+}
+
+#[test]
+fn create_raise_instantiates_the_pinned_template() {
+    let mut deps = setup();
+    let err = create_raise(&mut deps, "mallory", "first", template()).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+    let err = create_raise(&mut deps, OWNER, "", template()).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidRaiseName { .. }));
+
+    let response = create_raise(&mut deps, OWNER, "first", template()).unwrap();
+    assert_eq!(response.messages.len(), 1);
+    assert_eq!(response.messages[0].id, INSTANTIATE_REPLY_ID);
+    match &response.messages[0].msg {
+        CosmosMsg::Wasm(WasmMsg::Instantiate { admin, code_id, msg, .. }) => {
+            assert_eq!(admin.as_deref(), Some(OWNER));
+            assert_eq!(*code_id, CODE_ID);
+            let sent: RaiseInstantiateMsg = from_binary(msg).unwrap();
+            assert_eq!(sent.owner.as_deref(), Some(OWNER));
+            assert_eq!(sent.start_time, Some(NOW));
+            assert_eq!(sent.treasury_wallet, Addr::unchecked("treasury"));
+        },
+        msg => panic!("unexpected message {:?}", msg),
+    }
+}
+
+#[test]
+fn reply_registers_the_reported_address() {
+    let mut deps = setup();
+    create_raise(&mut deps, OWNER, "first", template()).unwrap();
+    let err = instantiated(&mut deps, None).unwrap_err();
+    assert!(matches!(err, ContractError::MissingRaiseAddress {}));
+
+    instantiated(&mut deps, Some("raise_zero")).unwrap();
+    let raise: RaiseResponse = from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetRaise { id: 0 }).unwrap()).unwrap();
+    assert_eq!(raise.name, "first");
+    assert_eq!(raise.address, Addr::unchecked("raise_zero"));
+    assert_eq!(raise.owner, Addr::unchecked(OWNER));
+    assert_eq!(raise.start_time, NOW);
+    assert_eq!(raise.created_at, NOW);
+}
+
+#[test]
+fn list_raises_pages_by_id() {
+    let mut deps = setup();
+    for (name, address) in [("first", "raise_zero"), ("second", "raise_one"), ("third", "raise_two")].iter() {
+        launch(&mut deps, name, address);
+    }
+
+    let ids = |raises: Vec<RaiseResponse>| raises.into_iter().map(|raise| raise.id).collect::<Vec<_>>();
+    assert_eq!(ids(list_raises(&deps, None, Some(2))), vec![0, 1]);
+    assert_eq!(ids(list_raises(&deps, Some(1), Some(2))), vec![2]);
+    assert_eq!(ids(list_raises(&deps, None, None)), vec![0, 1, 2]);
+}
@@ -0,0 +1,61 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Uint128};
+use cw2::set_contract_version;
+use crate::error::ContractError;
+use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use crate::state::{TokenInfo, TOKEN_INFO};
+use crate::handler::execute as ExecuteHandler;
+use crate::handler::query as QueryHandler;
+
+// version info for migration info
+const CONTRACT_NAME: &str = "crates.io:ipr-receipt";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    ExecuteHandler::validate_token_info(&msg.name, &msg.symbol, msg.decimals)?;
+    let token_info = TokenInfo {
+        name: msg.name,
+        symbol: msg.symbol,
+        decimals: msg.decimals,
+        total_supply: Uint128::zero(),
+        campaign: deps.api.addr_validate(&msg.campaign)?,
+        campaign_id: msg.campaign_id,
+    };
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    TOKEN_INFO.save(deps.storage, &token_info)?;
+    Ok(Response::new()
+        .add_attribute("method", "instantiate")
+        .add_attribute("campaign", token_info.campaign)
+        .add_attribute("campaign_id", token_info.campaign_id.to_string()))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::Transfer { recipient, amount } => ExecuteHandler::try_transfer(deps, info, recipient, amount),
+        ExecuteMsg::Send { contract, amount, msg } => ExecuteHandler::try_send(deps, info, contract, amount, msg),
+        ExecuteMsg::Burn { amount } => ExecuteHandler::try_burn(deps, info, amount),
+        ExecuteMsg::Mint { recipient, amount } => ExecuteHandler::try_mint(deps, info, recipient, amount),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Balance { address } => to_binary(&QueryHandler::query_balance(deps, address)?),
+        QueryMsg::Minter {} => to_binary(&QueryHandler::query_minter(deps)?),
+        QueryMsg::TokenInfo {} => to_binary(&QueryHandler::query_token_info(deps)?),
+    }
+}
@@ -0,0 +1,119 @@
+use cosmwasm_std::{to_binary, Addr, Binary, DepsMut, Event, MessageInfo, Response, Storage, Uint128, WasmMsg};
+use cw20::Cw20ReceiveMsg;
+use ipr::msg::ExecuteMsg as CampaignExecuteMsg;
+
+use crate::ContractError;
+use crate::state::{TokenInfo, BALANCES, TOKEN_INFO};
+
+// the same bounds cw20-base puts on a token's name, symbol and decimals
+pub fn validate_token_info(name: &str, symbol: &str, decimals: u8) -> Result<(), ContractError> {
+    let name_length = name.chars().count();
+    let symbol_length = symbol.chars().count();
+    if !(3..=50).contains(&name_length)
+        || !(3..=12).contains(&symbol_length)
+        || !symbol.chars().all(|c| c.is_ascii_alphabetic() || c == '-')
+        || decimals > 18 {
+        return Err(ContractError::InvalidTokenInfo {});
+    }
+    Ok(())
+}
+
+// receipts change hands and the campaign is told in the same transaction, so the position moves with them
+pub fn try_transfer(deps: DepsMut, info: MessageInfo, recipient: String, amount: Uint128) -> Result<Response, ContractError> {
+    let recipient = deps.api.addr_validate(&recipient)?;
+    let token_info = TOKEN_INFO.load(deps.storage)?;
+    move_balance(deps.storage, &info.sender, &recipient, amount)?;
+    Ok(Response::new()
+        .add_attribute("method", "try_transfer")
+        .add_message(transferred_msg(&token_info, &info.sender, &recipient, amount)?)
+        .add_event(transfer_event(&token_info, "ipr_receipt_transfer", &info.sender, &recipient, amount)))
+}
+
+// like a transfer, with the receiving contract called after the campaign has moved the position
+pub fn try_send(deps: DepsMut, info: MessageInfo, contract: String, amount: Uint128, msg: Binary) -> Result<Response, ContractError> {
+    let contract = deps.api.addr_validate(&contract)?;
+    let token_info = TOKEN_INFO.load(deps.storage)?;
+    move_balance(deps.storage, &info.sender, &contract, amount)?;
+    let receive = Cw20ReceiveMsg {
+        sender: info.sender.to_string(),
+        amount,
+        msg,
+    };
+    Ok(Response::new()
+        .add_attribute("method", "try_send")
+        .add_message(transferred_msg(&token_info, &info.sender, &contract, amount)?)
+        .add_message(receive.into_cosmos_msg(contract.to_string())?)
+        .add_event(transfer_event(&token_info, "ipr_receipt_send", &info.sender, &contract, amount)))
+}
+
+// the campaign burns the receipts sent back to redeem, nobody else holds the principal they stood for
+pub fn try_burn(deps: DepsMut, info: MessageInfo, amount: Uint128) -> Result<Response, ContractError> {
+    if amount.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    let mut token_info = TOKEN_INFO.load(deps.storage)?;
+    let balance = BALANCES.may_load(deps.storage, &info.sender)?.unwrap_or_default().checked_sub(amount)?;
+    BALANCES.save(deps.storage, &info.sender, &balance)?;
+    token_info.total_supply = token_info.total_supply.checked_sub(amount)?;
+    TOKEN_INFO.save(deps.storage, &token_info)?;
+    Ok(Response::new()
+        .add_attribute("method", "try_burn")
+        .add_event(Event::new("ipr_receipt_burn")
+            .add_attribute("campaign_id", token_info.campaign_id.to_string())
+            .add_attribute("from", info.sender.to_string())
+            .add_attribute("amount", amount.to_string())))
+}
+
+pub fn try_mint(deps: DepsMut, info: MessageInfo, recipient: String, amount: Uint128) -> Result<Response, ContractError> {
+    let mut token_info = TOKEN_INFO.load(deps.storage)?;
+    if info.sender != token_info.campaign {
+        return Err(ContractError::Unauthorized {});
+    }
+    if amount.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    let recipient = deps.api.addr_validate(&recipient)?;
+    let balance = BALANCES.may_load(deps.storage, &recipient)?.unwrap_or_default().checked_add(amount)?;
+    BALANCES.save(deps.storage, &recipient, &balance)?;
+    token_info.total_supply = token_info.total_supply.checked_add(amount)?;
+    TOKEN_INFO.save(deps.storage, &token_info)?;
+    Ok(Response::new()
+        .add_attribute("method", "try_mint")
+        .add_event(Event::new("ipr_receipt_mint")
+            .add_attribute("campaign_id", token_info.campaign_id.to_string())
+            .add_attribute("to", recipient.to_string())
+            .add_attribute("amount", amount.to_string())))
+}
+
+fn move_balance(storage: &mut dyn Storage, from: &Addr, to: &Addr, amount: Uint128) -> Result<(), ContractError> {
+    if amount.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    let from_balance = BALANCES.may_load(storage, from)?.unwrap_or_default().checked_sub(amount)?;
+    BALANCES.save(storage, from, &from_balance)?;
+    let to_balance = BALANCES.may_load(storage, to)?.unwrap_or_default().checked_add(amount)?;
+    BALANCES.save(storage, to, &to_balance)?;
+    Ok(())
+}
+
+// the campaign moves `amount` of principal from one entry to the other
+fn transferred_msg(token_info: &TokenInfo, from: &Addr, to: &Addr, amount: Uint128) -> Result<WasmMsg, ContractError> {
+    Ok(WasmMsg::Execute {
+        contract_addr: token_info.campaign.to_string(),
+        msg: to_binary(&CampaignExecuteMsg::ReceiptTransferred {
+            campaign_id: token_info.campaign_id,
+            from: from.to_string(),
+            to: to.to_string(),
+            amount,
+        })?,
+        funds: vec![],
+    })
+}
+
+fn transfer_event(token_info: &TokenInfo, ty: &str, from: &Addr, to: &Addr, amount: Uint128) -> Event {
+    Event::new(ty)
+        .add_attribute("campaign_id", token_info.campaign_id.to_string())
+        .add_attribute("from", from.to_string())
+        .add_attribute("to", to.to_string())
+        .add_attribute("amount", amount.to_string())
+}
@@ -0,0 +1,30 @@
+use cosmwasm_std::{Deps, StdResult};
+use cw20::{BalanceResponse, MinterResponse, TokenInfoResponse};
+
+use crate::state::{BALANCES, TOKEN_INFO};
+
+pub fn query_balance(deps: Deps, address: String) -> StdResult<BalanceResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    Ok(BalanceResponse {
+        balance: BALANCES.may_load(deps.storage, &address)?.unwrap_or_default(),
+    })
+}
+
+// the campaign mints without a cap, it mints exactly the principal deposited
+pub fn query_minter(deps: Deps) -> StdResult<Option<MinterResponse>> {
+    let token_info = TOKEN_INFO.load(deps.storage)?;
+    Ok(Some(MinterResponse {
+        minter: token_info.campaign.to_string(),
+        cap: None,
+    }))
+}
+
+pub fn query_token_info(deps: Deps) -> StdResult<TokenInfoResponse> {
+    let token_info = TOKEN_INFO.load(deps.storage)?;
+    Ok(TokenInfoResponse {
+        name: token_info.name,
+        symbol: token_info.symbol,
+        decimals: token_info.decimals,
+        total_supply: token_info.total_supply,
+    })
+}
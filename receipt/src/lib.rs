@@ -0,0 +1,10 @@
+pub mod contract;
+mod error;
+pub mod msg;
+pub mod state;
+mod handler;
+
+pub use crate::error::ContractError;
+
+#[cfg(test)]
+mod test;
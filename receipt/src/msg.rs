@@ -0,0 +1,33 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use cosmwasm_std::{Binary, Uint128};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InstantiateMsg {
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+    /// raise contract that mints the receipts and is told whenever they change hands
+    pub campaign: String,
+    pub campaign_id: u64,
+}
+
+// the cw20 calls a receipt needs, laid out like cw20's so the raise contract can mint and burn through
+// Cw20ExecuteMsg
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+    Transfer { recipient: String, amount: Uint128 },
+    Send { contract: String, amount: Uint128, msg: Binary },
+    Burn { amount: Uint128 },
+    Mint { recipient: String, amount: Uint128 },
+}
+
+// answered with cw20's BalanceResponse, MinterResponse and TokenInfoResponse
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    Balance { address: String },
+    Minter {},
+    TokenInfo {},
+}
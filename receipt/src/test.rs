@@ -0,0 +1,147 @@
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage};
+use cosmwasm_std::{from_binary, to_binary, Binary, CosmosMsg, OwnedDeps, Response, Uint128, WasmMsg};
+use cw20::{BalanceResponse, Cw20ReceiveMsg, MinterResponse, TokenInfoResponse};
+use ipr::msg::ExecuteMsg as CampaignExecuteMsg;
+use serde::de::DeserializeOwned;
+
+use crate::contract::{execute, instantiate, query};
+use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use crate::ContractError;
+
+const CAMPAIGN: &str = "campaign";
+const CAMPAIGN_ID: u64 = 3;
+
+type Deps = OwnedDeps<MockStorage, MockApi, MockQuerier>;
+
+fn instantiate_msg() -> InstantiateMsg {
+    InstantiateMsg {
+        name: "Interest Pool UST".to_string(),
+        symbol: "ipUST".to_string(),
+        decimals: 6,
+        campaign: CAMPAIGN.to_string(),
+        campaign_id: CAMPAIGN_ID,
+    }
+}
+
+// alice holds 1_000 receipts minted by the campaign
+fn setup() -> Deps {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(deps.as_mut(), mock_env(), mock_info("deployer", &[]), instantiate_msg()).unwrap();
+    run(&mut deps, CAMPAIGN, ExecuteMsg::Mint { recipient: "alice".to_string(), amount: Uint128::from(1_000u128) }).unwrap();
+    deps
+}
+
+fn run(deps: &mut Deps, sender: &str, msg: ExecuteMsg) -> Result<Response, ContractError> {
+    execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), msg)
+}
+
+fn fetch<T: DeserializeOwned>(deps: &Deps, msg: QueryMsg) -> T {
+    from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+}
+
+fn balance(deps: &Deps, address: &str) -> Uint128 {
+    let response: BalanceResponse = fetch(deps, QueryMsg::Balance { address: address.to_string() });
+    response.balance
+}
+
+fn total_supply(deps: &Deps) -> Uint128 {
+    let response: TokenInfoResponse = fetch(deps, QueryMsg::TokenInfo {});
+    response.total_supply
+}
+
+fn transferred(from: &str, to: &str, amount: u128) -> CosmosMsg {
+    CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: CAMPAIGN.to_string(),
+        msg: to_binary(&CampaignExecuteMsg::ReceiptTransferred {
+            campaign_id: CAMPAIGN_ID,
+            from: from.to_string(),
+            to: to.to_string(),
+            amount: Uint128::from(amount),
+        }).unwrap(),
+        funds: vec![],
+    })
+}
+
+#[test]
+fn token_info_and_minter_describe_the_receipt() {
+    let deps = setup();
+    let info: TokenInfoResponse = fetch(&deps, QueryMsg::TokenInfo {});
+    assert_eq!(info, TokenInfoResponse {
+        name: "Interest Pool UST".to_string(),
+        symbol: "ipUST".to_string(),
+        decimals: 6,
+        total_supply: Uint128::from(1_000u128),
+    });
+    // the raise contract checks it is the minter before registering the receipt
+    let minter: Option<MinterResponse> = fetch(&deps, QueryMsg::Minter {});
+    assert_eq!(minter, Some(MinterResponse { minter: CAMPAIGN.to_string(), cap: None }));
+}
+
+#[test]
+fn invalid_token_info_is_rejected() {
+    let mut deps = mock_dependencies(&[]);
+    let msg = InstantiateMsg { symbol: "ip$".to_string(), ..instantiate_msg() };
+    let err = instantiate(deps.as_mut(), mock_env(), mock_info("deployer", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidTokenInfo {}));
+    let msg = InstantiateMsg { decimals: 19, ..instantiate_msg() };
+    let err = instantiate(deps.as_mut(), mock_env(), mock_info("deployer", &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidTokenInfo {}));
+}
+
+#[test]
+fn only_the_campaign_mints() {
+    let mut deps = setup();
+    let err = run(&mut deps, "alice", ExecuteMsg::Mint { recipient: "alice".to_string(), amount: Uint128::from(1u128) }).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+    let res = run(&mut deps, CAMPAIGN, ExecuteMsg::Mint { recipient: "bob".to_string(), amount: Uint128::from(500u128) }).unwrap();
+    // minting backs a deposit the campaign already recorded, there is nothing to tell it
+    assert!(res.messages.is_empty());
+    assert_eq!(balance(&deps, "bob"), Uint128::from(500u128));
+    assert_eq!(total_supply(&deps), Uint128::from(1_500u128));
+}
+
+#[test]
+fn transfer_moves_the_position_with_the_receipts() {
+    let mut deps = setup();
+    let res = run(&mut deps, "alice", ExecuteMsg::Transfer { recipient: "bob".to_string(), amount: Uint128::from(400u128) }).unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(res.messages[0].msg, transferred("alice", "bob", 400));
+    assert_eq!(balance(&deps, "alice"), Uint128::from(600u128));
+    assert_eq!(balance(&deps, "bob"), Uint128::from(400u128));
+    assert_eq!(total_supply(&deps), Uint128::from(1_000u128));
+}
+
+#[test]
+fn transfer_cannot_overdraw_or_be_empty() {
+    let mut deps = setup();
+    let err = run(&mut deps, "alice", ExecuteMsg::Transfer { recipient: "bob".to_string(), amount: Uint128::from(1_001u128) }).unwrap_err();
+    assert!(matches!(err, ContractError::Overflow(_)));
+    let err = run(&mut deps, "alice", ExecuteMsg::Transfer { recipient: "bob".to_string(), amount: Uint128::zero() }).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidZeroAmount {}));
+    assert_eq!(balance(&deps, "alice"), Uint128::from(1_000u128));
+}
+
+#[test]
+fn send_tells_the_campaign_before_the_receiver() {
+    let mut deps = setup();
+    let hook = Binary::from(br#"{"redeem":{"campaign_id":3}}"#.to_vec());
+    let msg = ExecuteMsg::Send { contract: CAMPAIGN.to_string(), amount: Uint128::from(250u128), msg: hook.clone() };
+    let res = run(&mut deps, "alice", msg).unwrap();
+    let receive = Cw20ReceiveMsg { sender: "alice".to_string(), amount: Uint128::from(250u128), msg: hook };
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(res.messages[0].msg, transferred("alice", CAMPAIGN, 250));
+    assert_eq!(res.messages[1].msg, receive.into_cosmos_msg(CAMPAIGN.to_string()).unwrap());
+    assert_eq!(balance(&deps, CAMPAIGN), Uint128::from(250u128));
+}
+
+#[test]
+fn burn_takes_the_receipts_out_of_supply() {
+    let mut deps = setup();
+    run(&mut deps, "alice", ExecuteMsg::Transfer { recipient: CAMPAIGN.to_string(), amount: Uint128::from(300u128) }).unwrap();
+    let res = run(&mut deps, CAMPAIGN, ExecuteMsg::Burn { amount: Uint128::from(300u128) }).unwrap();
+    assert!(res.messages.is_empty());
+    assert_eq!(balance(&deps, CAMPAIGN), Uint128::zero());
+    assert_eq!(total_supply(&deps), Uint128::from(700u128));
+    let err = run(&mut deps, "bob", ExecuteMsg::Burn { amount: Uint128::from(1u128) }).unwrap_err();
+    assert!(matches!(err, ContractError::Overflow(_)));
+}
@@ -1,12 +1,15 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+use cosmwasm_std::{to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult};
 use cw2::set_contract_version;
 use crate::error::ContractError;
 use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
-use crate::state::{State, STATE};
+use crate::state::{Config, CONFIG};
+use crate::handler::core as CoreHandler;
 use crate::handler::execute as ExecuteHandler;
 use crate::handler::query as QueryHandler;
+use crate::handler::reply as ReplyHandler;
+use crate::handler::simulate as SimulateHandler;
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:ipr";
@@ -15,36 +18,30 @@ const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
-    let state = State {
-        owner: info.sender.clone(),
-        treasury_wallet: info.sender.clone(),
-        reward_contract: info.sender.clone(),
-        ust_deposited: msg.ust_deposited,
-        sellback_price: msg.sellback_price,
-        anc_market: msg.anc_market,
-        aust_contract: msg.aust_contract,
-        tier0rate: msg.tier0rate,
-        tier0time: msg.tier0time,
-        tier1rate: msg.tier1rate,
-        tier1time: msg.tier1time,
-        tier2rate: msg.tier2rate,
-        tier2time: msg.tier2time,
-        tier3rate: msg.tier3rate,
-        tier3time: msg.tier3time,
+    // the first campaign's owner can open further campaigns, the first one is opened straight away
+    let config = Config {
+        owner: match &msg.owner {
+            Some(owner) => deps.api.addr_validate(owner)?,
+            None => info.sender.clone(),
+        },
+        campaign_count: 0,
     };
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
-    STATE.save(deps.storage, &state)?;
+    CONFIG.save(deps.storage, &config)?;
+    let campaign = ExecuteHandler::create_campaign(deps, &env, &info, msg)?;
     Ok(Response::new()
         .add_attribute("method", "instantiate")
-        .add_attribute("owner", state.owner)
-        .add_attribute("treasury_wallet", state.treasury_wallet)
-        .add_attribute("reward_contract", state.reward_contract)
-        .add_attribute("ust_deposited", state.ust_deposited.to_string())
-        .add_attribute("sellback_price", state.sellback_price.to_string()))
+        .add_attribute("campaign_id", campaign.id.to_string())
+        .add_attribute("owner", campaign.owner)
+        .add_attribute("treasury_wallet", campaign.treasury_wallet)
+        .add_attribute("reward_contract", campaign.reward_contract)
+        .add_attribute("reward_token", campaign.reward_token)
+        .add_attribute("ust_deposited", campaign.ust_deposited.to_string())
+        .add_attribute("sellback_price", campaign.sellback_price.to_string()))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -55,23 +52,77 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::DepositUst { entry_address, amount } => ExecuteHandler::try_deposit(deps, info, _env, entry_address, amount),
-        ExecuteMsg::WithdrawUst { entry_address, amount } => ExecuteHandler::try_withdraw(deps, info, _env, entry_address, amount),
-        ExecuteMsg::ClaimReward { entry_address } => ExecuteHandler::try_claim(deps, info, entry_address),
-        ExecuteMsg::SellReward { entry_address, amount } => ExecuteHandler::try_sell(deps, info, _env, entry_address, amount),  
-        ExecuteMsg::UpdateEntries {} => ExecuteHandler::try_update_entries(deps, info),  
-        ExecuteMsg::CashoutYield {} => ExecuteHandler::try_cashout_yield(deps, info),
-        ExecuteMsg::SetTreasuryWallet { address } => ExecuteHandler::try_set_treasury_wallet(deps, info, address),
-        ExecuteMsg::SetRewardContract {address } => ExecuteHandler::try_set_reward_contract(deps, info, address),
-        ExecuteMsg::SetTierData { data } => ExecuteHandler::try_set_tier_data(deps, info, data),
-        ExecuteMsg::SetAncMarket { address } => ExecuteHandler::try_set_anc_market(deps, info, address),
+        ExecuteMsg::CreateCampaign(msg) => ExecuteHandler::try_create_campaign(deps, _env, info, *msg),
+        ExecuteMsg::DepositUst { campaign_id, entry_address, amount, referrer } => ExecuteHandler::try_deposit(deps, info, _env, campaign_id, entry_address, amount, referrer),
+        ExecuteMsg::WithdrawUst { campaign_id, entry_address, amount, order } => ExecuteHandler::try_withdraw(deps, info, _env, campaign_id, entry_address, amount, order),
+        ExecuteMsg::ClaimReward { campaign_id, entry_address } => ExecuteHandler::try_claim(deps, info, _env, campaign_id, entry_address),
+        ExecuteMsg::SellReward { campaign_id, entry_address, amount, on_excess } => ExecuteHandler::try_sell(deps, info, _env, campaign_id, entry_address, amount, on_excess),
+        ExecuteMsg::UpdateEntries { campaign_id, start_after, limit } => ExecuteHandler::try_update_entries(deps, info, _env, campaign_id, start_after, limit),
+        ExecuteMsg::CashoutYield { campaign_id } => ExecuteHandler::try_cashout_yield(deps, info, _env, campaign_id),
+        ExecuteMsg::FundRewards { campaign_id, amount } => ExecuteHandler::try_fund_rewards(deps, info, _env, campaign_id, amount),
+        ExecuteMsg::SetTreasuryWallet { campaign_id, address } => ExecuteHandler::try_set_treasury_wallet(deps, info, campaign_id, address),
+        ExecuteMsg::SetRewardContract { campaign_id, address } => ExecuteHandler::try_set_reward_contract(deps, info, campaign_id, address),
+        ExecuteMsg::SetRewardToken { campaign_id, address } => ExecuteHandler::try_set_reward_token(deps, info, campaign_id, address),
+        ExecuteMsg::SetRewardMode { campaign_id, mode } => ExecuteHandler::try_set_reward_mode(deps, info, campaign_id, mode),
+        ExecuteMsg::SetTierData { campaign_id, data } => ExecuteHandler::try_set_tier_data(deps, info, _env, campaign_id, data),
+        ExecuteMsg::SetAncMarket { campaign_id, address } => ExecuteHandler::try_set_anc_market(deps, info, campaign_id, address),
+        ExecuteMsg::SetSellbackPrice { campaign_id, price } => ExecuteHandler::try_set_sellback_price(deps, info, campaign_id, price),
+        ExecuteMsg::SetSellbackSource { campaign_id, source, floor, ceiling } => ExecuteHandler::try_set_sellback_source(deps, info, campaign_id, source, floor, ceiling),
+        ExecuteMsg::RecordSellbackPrice { campaign_id } => ExecuteHandler::try_record_sellback_price(deps, info, _env, campaign_id),
+        ExecuteMsg::SetYieldSplit { campaign_id, split } => ExecuteHandler::try_set_yield_split(deps, info, campaign_id, split),
+        ExecuteMsg::SetSellLimits { campaign_id, epoch_length, global_cap, address_cap } => ExecuteHandler::try_set_sell_limits(deps, info, campaign_id, epoch_length, global_cap, address_cap),
+        ExecuteMsg::ProcessSellQueue { campaign_id, start_after, limit } => ExecuteHandler::try_process_sell_queue(deps, info, _env, campaign_id, start_after, limit),
+        ExecuteMsg::CancelQueuedSell { campaign_id, id } => ExecuteHandler::try_cancel_queued_sell(deps, info, campaign_id, id),
+        ExecuteMsg::SetReferralTerms { campaign_id, share, period } => ExecuteHandler::try_set_referral_terms(deps, info, campaign_id, share, period),
+        ExecuteMsg::SetAutoCompound { campaign_id, entry_address, enabled } => ExecuteHandler::try_set_auto_compound(deps, info, _env, campaign_id, entry_address, enabled),
+        ExecuteMsg::StakeReward { campaign_id, entry_address, amount } => ExecuteHandler::try_stake_reward(deps, info, _env, campaign_id, entry_address, amount),
+        ExecuteMsg::UnstakeReward { campaign_id, entry_address, amount } => ExecuteHandler::try_unstake_reward(deps, info, _env, campaign_id, entry_address, amount),
+        ExecuteMsg::SetStakeTerms { campaign_id, boost_rate, boost_cap, unstake_cooldown } => ExecuteHandler::try_set_stake_terms(deps, info, campaign_id, boost_rate, boost_cap, unstake_cooldown),
+        ExecuteMsg::SetPartnerBoost { campaign_id, contract, kind, brackets } => ExecuteHandler::try_set_partner_boost(deps, info, campaign_id, contract, kind, brackets),
+        ExecuteMsg::RemovePartnerBoost { campaign_id, contract } => ExecuteHandler::try_remove_partner_boost(deps, info, campaign_id, contract),
+        ExecuteMsg::Receive(msg) => CoreHandler::receive(deps, _env, info, msg),
+        ExecuteMsg::RegisterDpToken { campaign_id, address } => CoreHandler::register_dp_token(deps, _env, info, campaign_id, address),
+        ExecuteMsg::ReceiptTransferred { campaign_id, from, to, amount } => ExecuteHandler::try_receipt_transferred(deps, info, _env, campaign_id, from, to, amount),
+        ExecuteMsg::TransferEntry { campaign_id, to, amount } => ExecuteHandler::try_transfer_entry(deps, info, _env, campaign_id, to, amount),
+        ExecuteMsg::SetDepletionOrder { campaign_id, order } => ExecuteHandler::try_set_depletion_order(deps, info, campaign_id, order),
+        ExecuteMsg::SetCampaignWindow { campaign_id, start_time, end_time } => ExecuteHandler::try_set_campaign_window(deps, info, campaign_id, start_time, end_time),
+        ExecuteMsg::SetDepositCap { campaign_id, cap } => ExecuteHandler::try_set_deposit_cap(deps, info, campaign_id, cap),
+        ExecuteMsg::SetSuccessCriteria { campaign_id, soft_cap, deadline } => ExecuteHandler::try_set_success_criteria(deps, info, _env, campaign_id, soft_cap, deadline),
+        ExecuteMsg::Finalize { campaign_id } => ExecuteHandler::try_finalize(deps, info, _env, campaign_id),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        ExecuteHandler::CLAIM_REPLY_ID => ReplyHandler::reply_claim(deps, msg),
+        id => Err(ContractError::UnknownReplyId { id }),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
-        QueryMsg::GetEntry { entry_address } => to_binary(&QueryHandler::query_entry(deps, entry_address)?),
-        QueryMsg::GetState {} => to_binary(&QueryHandler::query_state(deps)?),
+        QueryMsg::GetEntry { campaign_id, entry_address } => to_binary(&QueryHandler::query_entry(deps, campaign_id, entry_address)?),
+        QueryMsg::GetConfig {} => to_binary(&QueryHandler::query_config(deps)?),
+        QueryMsg::GetCampaign { campaign_id } => to_binary(&QueryHandler::query_campaign(deps, campaign_id)?),
+        QueryMsg::ListCampaigns { start_after, limit } => to_binary(&QueryHandler::query_campaigns(deps, start_after, limit)?),
+        QueryMsg::SimulateDeposit { campaign_id, entry_address, amount } => to_binary(&SimulateHandler::simulate_deposit(deps, env, campaign_id, entry_address, amount)?),
+        QueryMsg::SimulateWithdraw { campaign_id, entry_address, amount, order } => to_binary(&SimulateHandler::simulate_withdraw(deps, env, campaign_id, entry_address, amount, order)?),
+        QueryMsg::SimulateSell { campaign_id, entry_address, amount } => to_binary(&SimulateHandler::simulate_sell(deps, env, campaign_id, entry_address, amount)?),
+        QueryMsg::SimulateClaim { campaign_id, entry_address } => to_binary(&SimulateHandler::simulate_claim(deps, env, campaign_id, entry_address)?),
+        QueryMsg::GetProjectedRewards { campaign_id, entry_address, time } => to_binary(&QueryHandler::query_projected_rewards(deps, env, campaign_id, entry_address, time)?),
+        QueryMsg::GetStats { campaign_id } => to_binary(&QueryHandler::query_stats(deps, env, campaign_id)?),
+        QueryMsg::GetRewardBudget { campaign_id } => to_binary(&QueryHandler::query_reward_budget(deps, env, campaign_id)?),
+        QueryMsg::GetSellbackPrice { campaign_id } => to_binary(&QueryHandler::query_sellback_price(deps, env, campaign_id)?),
+        QueryMsg::GetSellbackReserve { campaign_id } => to_binary(&QueryHandler::query_sellback_reserve(deps, env, campaign_id)?),
+        QueryMsg::GetSellAllowance { campaign_id, entry_address } => to_binary(&QueryHandler::query_sell_allowance(deps, env, campaign_id, entry_address)?),
+        QueryMsg::GetSellQueue { campaign_id, start_after, limit } => to_binary(&QueryHandler::query_sell_queue(deps, campaign_id, start_after, limit)?),
+        QueryMsg::GetReferral { campaign_id, entry_address } => to_binary(&QueryHandler::query_referral(deps, campaign_id, entry_address)?),
+        QueryMsg::GetReferrer { campaign_id, address } => to_binary(&QueryHandler::query_referrer(deps, campaign_id, address)?),
+        QueryMsg::GetPartnerBoosts { campaign_id } => to_binary(&QueryHandler::query_partner_boosts(deps, campaign_id)?),
+        QueryMsg::GetDeposited { campaign_id, entry_address, height } => to_binary(&QueryHandler::query_deposited(deps, campaign_id, entry_address, height)?),
+        QueryMsg::GetTotalDeposited { campaign_id, height } => to_binary(&QueryHandler::query_total_deposited(deps, campaign_id, height)?),
+        QueryMsg::TopDepositors { campaign_id, limit, by } => to_binary(&QueryHandler::query_top_depositors(deps, env, campaign_id, limit, by)?),
     }
 }
\ No newline at end of file
@@ -1,4 +1,4 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{OverflowError, StdError, Uint128};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -6,6 +6,9 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
@@ -21,6 +24,132 @@ pub enum ContractError {
     #[error("You cannot claim rewards without depositing UST")]
     CannotClaimWithoutDeposit {},
 
+    #[error("You must send exactly the amount of UST you are depositing")]
+    DepositFundsMismatch {},
+
+    #[error("Unknown reply id {id}")]
+    UnknownReplyId { id: u64 },
+
+    #[error("The sellback price source is not a time-weighted average")]
+    SellbackSourceNotTwap {},
+
+    #[error("The sellback price floor cannot be above its ceiling")]
+    InvalidSellbackBand {},
+
+    #[error("A sellback price read from a pair needs a ceiling")]
+    SellbackCeilingRequired {},
+
+    #[error("The next sellback price observation can be recorded from {time} on")]
+    ObservationTooSoon { time: u64 },
+
+    #[error("The sellback price was last observed {age} seconds ago")]
+    StaleSellbackPrice { age: u64 },
+
+    #[error("Not enough price observations to average the sellback price")]
+    InsufficientPriceObservations {},
+
+    #[error("There is no yield to cash out")]
+    NoYieldToCashout {},
+
+    #[error("The stake boost cap must be at least the boost rate and at most ten, the unstake cooldown at most 90 days")]
+    InvalidStakeTerms {},
+
     #[error("Invalid zero amount")]
     InvalidZeroAmount {},
+
+    #[error("Tier 0 must start at deposit and each later tier must start after the one below it")]
+    InvalidTierTimes {},
+
+    #[error("A tier cannot pay a lower rate than the tier below it")]
+    InvalidTierRates {},
+
+    #[error("The sellback price must be above zero")]
+    InvalidSellbackPrice {},
+
+    #[error("The time-weighted average window and staleness limit must be above zero")]
+    InvalidTwapWindow {},
+
+    #[error("Yield shares have to be above zero, name each recipient once and add up to one")]
+    InvalidYieldSplit {},
+
+    #[error("The sellback reserve only holds {reserve} uust")]
+    InsufficientSellbackReserve { reserve: Uint128 },
+
+    #[error("The sell epoch must be longer than zero seconds")]
+    InvalidSellEpoch {},
+
+    #[error("Only {available} MIN can be sold back this epoch")]
+    SellLimitExceeded { available: Uint128 },
+
+    #[error("At most {max} sells can be queued per address")]
+    TooManyQueuedSells { max: u32 },
+
+    #[error("The referral share cannot be above one")]
+    InvalidReferralShare {},
+
+    #[error("An entry cannot refer itself")]
+    InvalidReferrer {},
+
+    #[error("A referrer can only be given on an entry's first deposit")]
+    ReferralOnlyOnFirstDeposit {},
+
+    #[error("Cannot stake more than the claimable reward")]
+    CannotStakeGreaterThanClaimable {},
+
+    #[error("Cannot unstake more than the staked reward")]
+    CannotUnstakeGreaterThanStaked {},
+
+    #[error("Boost brackets must climb in holding without lowering the boost")]
+    InvalidBoostBrackets {},
+
+    #[error("A receipt token is already registered")]
+    DpTokenAlreadyRegistered {},
+
+    #[error("A receipt token can only be registered before anyone deposits")]
+    DpTokenAfterDeposits {},
+
+    #[error("This contract has to be the receipt token's minter")]
+    DpTokenNotMinter {},
+
+    #[error("Positions move with the receipt token once one is registered")]
+    TransferWithReceiptToken {},
+
+    #[error("Principal is withdrawn by redeeming receipts once a receipt token is registered")]
+    WithdrawWithReceiptToken {},
+
+    #[error("Cannot transfer a position to the address it is held by")]
+    InvalidTransferRecipient {},
+
+    #[error("The campaign is not taking deposits")]
+    CampaignNotOpen {},
+
+    #[error("The campaign can only take {available} uust more")]
+    DepositCapExceeded { available: Uint128 },
+
+    #[error("A campaign has to end after it starts")]
+    InvalidCampaignWindow {},
+
+    #[error("A soft cap and a deadline after the campaign's start have to be set together")]
+    InvalidSuccessCriteria {},
+
+    #[error("A soft cap only counts real deposits, it can't be added to principal that is already counted")]
+    SoftCapWithDeposits {},
+
+    #[error("The campaign has no soft cap to finalize against")]
+    NoSuccessCriteria {},
+
+    #[error("The campaign cannot be finalized before {deadline}")]
+    DeadlineNotReached { deadline: u64 },
+
+    #[error("The campaign has already been finalized")]
+    CampaignFinalized {},
+
+    #[error("The campaign's rewards were voided when it failed")]
+    RewardsVoided {},
+
+    #[error("Rewards are held until the campaign has succeeded")]
+    RewardsHeldUntilSuccess {},
+
+    #[error("Yield stays with the depositors until the campaign has succeeded")]
+    YieldHeldUntilSuccess {},
 }
@@ -1,6 +1,6 @@
 use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::*;
-use cw20::Cw20ExecuteMsg;
+use cw20::{BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use terra_cosmwasm::TerraQuerier;
@@ -35,6 +35,7 @@ pub enum QueryMsg {
 }
 
 // We define a custom struct for each query response
+#[allow(dead_code)]
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct ConfigResponse {
     pub owner_addr: String,
@@ -69,6 +70,24 @@ pub enum Cw20HookMsg {
     RedeemStable {},
 }
 
+pub fn epoch_state(deps: Deps, market: String, block_height: u64) -> StdResult<EpochStateResponse> {
+    deps.querier.query_wasm_smart(
+        deps.api.addr_validate(&market)?,
+        &QueryMsg::EpochState {
+            block_height: Some(block_height),
+            distributed_interest: None,
+        },
+    )
+}
+
+pub fn aust_balance(deps: Deps, token: String, holder: String) -> StdResult<Uint128> {
+    let response: BalanceResponse = deps.querier.query_wasm_smart(
+        deps.api.addr_validate(&token)?,
+        &Cw20QueryMsg::Balance { address: holder },
+    )?;
+    Ok(response.balance)
+}
+
 pub fn deposit_stable_msg(deps: Deps, market: String, denom: &str, amount: Uint128,) -> StdResult<Vec<CosmosMsg>> {
     Ok(vec![CosmosMsg::Wasm(WasmMsg::Execute {
         contract_addr: deps.api.addr_validate(&market).unwrap().to_string(),
@@ -1,259 +1,1525 @@
-use cosmwasm_std::{DepsMut, Response, MessageInfo, StdResult, Uint128, Env, SubMsg, BankMsg, coins, to_binary, Addr, CosmosMsg, WasmMsg, Deps};
-use crate::{ContractError, state::{ENTRIES, STATE, Entry}};
-use crate::handler::anchor;
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{DepsMut, Response, MessageInfo, StdResult, Uint128, Env, Event, StdError, SubMsg, BankMsg, coin, to_binary, to_vec, Addr, CosmosMsg, WasmMsg, Deps, Order, OverflowError, OverflowOperation, Storage};
 use cw20::Cw20ExecuteMsg;
+use std::cmp::Reverse;
+
+use serde::Serialize;
+use cw_storage_plus::{Bound, U64Key};
+use crate::{ContractError, msg::{InstantiateMsg, RewardContractMsg, SellExcess, WithdrawResponse}, state::{campaign_key, entries, CAMPAIGNS, CONFIG, REFERRALS, PENDING_CLAIM, PRICE_OBSERVATIONS, SELL_QUEUE, SELL_QUEUE_NEXT_ID, Entry, PendingClaim, DepletionOrder, PriceSource, QueuedSell, RewardMode, Campaign, CampaignStatus, TierCheckpoint, Unbonding, YieldRecipient, YieldShare, BoostBracket, PartnerBoost, PartnerKind, PARTNER_BOOSTS, DEPOSITED, TOTAL_DEPOSITED}};
+use crate::handler::{anchor, limit, partner, price, referral, reward, split};
+use crate::handler::math::{ratio, to_uint128};
 use crate::{state::{Deposit, Reward, Withdraw}};
 
-pub fn try_deposit(deps: DepsMut, info: MessageInfo, env: Env, entry_address: String, amount: Uint128) -> Result<Response, ContractError> {
-    let state = STATE.load(deps.storage)?;
+pub const CLAIM_REPLY_ID: u64 = 1;
+
+const DEFAULT_QUEUE_LIMIT: u32 = 10;
+const MAX_QUEUE_LIMIT: u32 = 30;
+const MAX_QUEUE_SCAN: usize = 100;
+
+const DEFAULT_ENTRIES_LIMIT: u32 = 10;
+const MAX_ENTRIES_LIMIT: u32 = 30;
+
+// sell caps reset daily until the owner sets otherwise
+const DEFAULT_SELL_EPOCH_LENGTH: u64 = 86_400;
+
+// stake terms an owner can set: a boost of at most ten and a cooldown of at most 90 days
+const MAX_STAKE_BOOST_PERCENT: u64 = 1_000;
+const MAX_UNSTAKE_COOLDOWN: u64 = 90 * 86_400;
+
+pub fn try_create_campaign(deps: DepsMut, env: Env, info: MessageInfo, msg: InstantiateMsg) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    let campaign = create_campaign(deps, &env, &info, msg)?;
+    Ok(Response::new()
+        .add_attribute("method", "try_create_campaign")
+        .add_event(Event::new("ipr_campaign_created")
+            .add_attribute("campaign_id", campaign.id.to_string())
+            .add_attribute("owner", campaign.owner.to_string())
+            .add_attribute("reward_token", campaign.reward_token.to_string())
+            .add_attribute("start_time", campaign.start_time.to_string())
+            .add_attribute("end_time", to_json_string(&campaign.end_time)?)
+            .add_attribute("deposit_cap", to_json_string(&campaign.deposit_cap)?)
+            .add_attribute("soft_cap", to_json_string(&campaign.soft_cap)?)
+            .add_attribute("deadline", to_json_string(&campaign.deadline)?)))
+}
+
+// opens a campaign under the next id, run by its sender unless the terms name another owner
+pub fn create_campaign(deps: DepsMut, env: &Env, info: &MessageInfo, msg: InstantiateMsg) -> Result<Campaign, ContractError> {
+    msg.validate()?;
+    let mut config = CONFIG.load(deps.storage)?;
+    let owner = match &msg.owner {
+        Some(owner) => deps.api.addr_validate(owner)?,
+        None => info.sender.clone(),
+    };
+    let campaign = Campaign {
+        id: config.campaign_count,
+        owner: owner.clone(),
+        treasury_wallet: deps.api.addr_validate(msg.treasury_wallet.as_str())?,
+        yield_split: match msg.yield_split {
+            Some(yield_split) => split::validated_yield_split(deps.api, yield_split)?,
+            None => vec![YieldShare { recipient: YieldRecipient::Treasury {}, share: Decimal256::one() }],
+        },
+        reward_contract: deps.api.addr_validate(msg.reward_contract.as_str())?,
+        reward_token: deps.api.addr_validate(&msg.reward_token)?,
+        reward_mode: msg.reward_mode,
+        dp_token: None,
+        ust_deposited: msg.ust_deposited,
+        start_time: msg.start_time.unwrap_or_else(|| env.block.time.seconds()),
+        end_time: msg.end_time,
+        deposit_cap: msg.deposit_cap,
+        soft_cap: msg.soft_cap,
+        deadline: msg.deadline,
+        status: CampaignStatus::Active,
+        aust_balance: Uint128::zero(),
+        depletion_order: DepletionOrder::OldestFirst,
+        sellback_price: msg.sellback_price,
+        sellback_source: PriceSource::Static {},
+        sellback_floor: Decimal256::zero(),
+        sellback_ceiling: None,
+        anc_market: msg.anc_market,
+        aust_contract: msg.aust_contract,
+        tier0rate: msg.tier0rate,
+        tier0time: msg.tier0time,
+        tier1rate: msg.tier1rate,
+        tier1time: msg.tier1time,
+        tier2rate: msg.tier2rate,
+        tier2time: msg.tier2time,
+        tier3rate: msg.tier3rate,
+        tier3time: msg.tier3time,
+        tier_history: vec![],
+        yield_cashed_out: Uint128::zero(),
+        sellback_reserve: Uint128::zero(),
+        sell_epoch_length: DEFAULT_SELL_EPOCH_LENGTH,
+        sell_global_cap: None,
+        sell_address_cap: None,
+        referral_share: Decimal256::zero(),
+        referral_period: 0,
+        stake_boost_rate: Decimal256::zero(),
+        stake_boost_cap: Decimal256::zero(),
+        unstake_cooldown: 0,
+        reward_budget: Uint128::zero(),
+        tier_principal: [Decimal256::zero(); 4],
+        tier_index: [Decimal256::zero(); 4],
+        outstanding_reward: Uint128::zero(),
+        outstanding_time: 0,
+        total_reward_accrued: Uint128::zero(),
+        total_reward_claimed: Uint128::zero(),
+        total_reward_bought_back: Uint128::zero(),
+        depositor_count: 0,
+    };
+    validate_campaign_window(campaign.start_time, campaign.end_time)?;
+    validate_success_criteria(campaign.start_time, campaign.soft_cap, campaign.deadline)?;
+    config.campaign_count += 1;
+    CONFIG.save(deps.storage, &config)?;
+    CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+    TOTAL_DEPOSITED.save(deps.storage, U64Key::new(campaign.id), &campaign.ust_deposited, env.block.height)?;
+    Ok(campaign)
+}
+
+pub fn try_deposit(mut deps: DepsMut, info: MessageInfo, env: Env, campaign_id: u64, entry_address: String, amount: Uint128, referrer: Option<String>) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
     let valid_address = deps.api.addr_validate(&entry_address)?;
     let time = env.block.time.seconds();
-    
-    let upsert_entry = |entry: Option<Entry>| -> StdResult<Entry> {
-        match entry {
-            Some(entry) => {some_deposit_helper(entry, amount, time)},
-            None => {none_deposit_helper(amount, time)},
+    let mut response = Response::new();
+
+    if info.sender == entry_address || info.sender == campaign.owner {
+        if !taking_deposits(&campaign, time) {
+            return Err(ContractError::CampaignNotOpen {});
         }
-    };
-    
-    if info.sender == entry_address || info.sender == state.owner {
-        make_deposit_and_convert_to_aust(deps.as_ref(), env, amount)?;
-        ENTRIES.update(deps.storage, &valid_address, upsert_entry)?;
+        if let Some(deposit_cap) = campaign.deposit_cap {
+            let available = deposit_cap.saturating_sub(campaign.ust_deposited);
+            if amount > available {
+                return Err(ContractError::DepositCapExceeded { available });
+            }
+        }
+        response = response.add_messages(make_deposit_and_convert_to_aust(deps.as_ref(), &env, &info, &mut campaign, amount)?);
+        let existing = entries().may_load(deps.storage, campaign_key(campaign.id, &valid_address))?;
+        if let Some(referrer) = referrer {
+            let referrer = deps.api.addr_validate(&referrer)?;
+            if referrer == valid_address {
+                return Err(ContractError::InvalidReferrer {});
+            }
+            if existing.is_some() || REFERRALS.has(deps.storage, campaign_key(campaign.id, &valid_address)) {
+                return Err(ContractError::ReferralOnlyOnFirstDeposit {});
+            }
+            let referral = referral::record_referral(deps.storage, &campaign, &valid_address, referrer, time)?;
+            response = response.add_event(Event::new("ipr_referral")
+                .add_attribute("campaign_id", campaign.id.to_string())
+                .add_attribute("referrer", referral.referrer.to_string())
+                .add_attribute("referee", valid_address.to_string())
+                .add_attribute("share", referral.share.to_string())
+                .add_attribute("end", referral.end.to_string()));
+        }
+        let mut entry = match existing {
+            Some(mut entry) => {
+                response = response.add_events(settle(&mut deps, &mut campaign, &mut entry, &valid_address, time, true)?);
+                if entry.ust_deposited.is_zero() {
+                    add_depositor(&mut campaign)?;
+                }
+                some_deposit_helper(entry, amount, time)?
+            },
+            None => {
+                add_depositor(&mut campaign)?;
+                let mut entry = none_deposit_helper(amount, time)?;
+                partner::refresh_boost(deps.as_ref(), campaign.id, &mut entry, &valid_address)?;
+                entry
+            },
+        };
+        save_entry(deps.storage, &mut campaign, &valid_address, &mut entry, time)?;
+        campaign.ust_deposited = campaign.ust_deposited.checked_add(amount)?;
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+        // the receipt token stands for the principal, whoever holds it owns the position
+        if let Some(dp_token) = &campaign.dp_token {
+            response = response.add_message(WasmMsg::Execute {
+                contract_addr: dp_token.to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Mint {
+                    recipient: valid_address.to_string(),
+                    amount,
+                })?,
+                funds: vec![],
+            });
+        }
+        snapshot_deposits(deps.storage, &valid_address, &entry, &campaign, env.block.height)?;
+        response = response.add_event(Event::new("ipr_deposit")
+            .add_attribute("campaign_id", campaign.id.to_string())
+            .add_attribute("entry_address", valid_address.to_string())
+            .add_attribute("sender", info.sender.to_string())
+            .add_attribute("amount", amount.to_string())
+            .add_attribute("denom", "uust")
+            .add_attribute("tranche_id", (entry.dynamic_reward_log.len() - 1).to_string())
+            .add_attribute("reward_tier", "0")
+            .add_attribute("ust_deposited", entry.ust_deposited.to_string()));
     } else {
         return Err(ContractError::Unauthorized {});
     }
-    Ok(Response::new().add_attribute("method", "try_deposit"))
+    Ok(response.add_attribute("method", "try_deposit"))
 }
 
-pub fn try_withdraw(deps: DepsMut, info: MessageInfo, env: Env, entry_address: String, amount: Uint128) -> Result<Response, ContractError> {
-    let state = STATE.load(deps.storage)?;
+pub fn try_withdraw(deps: DepsMut, info: MessageInfo, env: Env, campaign_id: u64, entry_address: String, amount: Uint128, order: Option<DepletionOrder>) -> Result<Response, ContractError> {
+    let campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
     let valid_address = deps.api.addr_validate(&entry_address)?;
-    let time = env.block.time.seconds();
-    let update_entry = |entry: Option<Entry>| -> Result<Entry, ContractError> {
-        match entry {
-            Some(entry) => some_withdraw_helper(entry, time, amount),
-            None => Err(ContractError::CannotWithdrawWithoutDeposit {}),
+
+    if info.sender == entry_address || info.sender == campaign.owner {
+        // with a receipt token the principal goes to whoever holds the receipts, so it only comes out
+        // through the redeem hook
+        if campaign.dp_token.is_some() {
+            return Err(ContractError::WithdrawWithReceiptToken {});
         }
+        let order = order.unwrap_or_else(|| campaign.depletion_order.clone());
+        let response = withdraw_position(deps, &env, campaign_id, &info.sender, &valid_address, amount, &order)?;
+        Ok(response.add_attribute("method", "try_withdraw"))
+    } else {
+        Err(ContractError::Unauthorized {})
+    }
+}
+
+// settles the entry, takes `amount` of principal off its tranches in `order` and sends it back out of anchor,
+// reporting the tranches it took and the log left behind as the response data
+pub fn withdraw_position(mut deps: DepsMut, env: &Env, campaign_id: u64, sender: &Addr, address: &Addr, amount: Uint128, order: &DepletionOrder) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let time = env.block.time.seconds();
+    let mut response = Response::new();
+
+    let mut entry = entries().may_load(deps.storage, campaign_key(campaign.id, address))?
+        .ok_or(ContractError::CannotWithdrawWithoutDeposit {})?;
+    response = response.add_events(settle(&mut deps, &mut campaign, &mut entry, address, time, false)?);
+    let before = entry.dynamic_reward_log.clone();
+    let mut entry = some_withdraw_helper(entry, time, amount, order)?;
+    if entry.ust_deposited.is_zero() {
+        remove_depositor(&mut campaign)?;
+    }
+    // a failed campaign pays the principal back with its share of the yield
+    let (messages, ust_amount) = if campaign.status == CampaignStatus::Failed {
+        refund_from_aust(deps.as_ref(), env, &mut campaign, address, amount)?
+    } else {
+        (convert_from_aust_and_make_withdraw(deps.as_ref(), env, &mut campaign, address, amount)?, amount)
+    };
+    response = response.add_messages(messages);
+    save_entry(deps.storage, &mut campaign, address, &mut entry, time)?;
+    campaign.ust_deposited = campaign.ust_deposited.checked_sub(amount)?;
+    CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+    snapshot_deposits(deps.storage, address, &entry, &campaign, env.block.height)?;
+
+    let tranche_ids: Vec<String> = before.iter()
+        .zip(entry.dynamic_reward_log.iter())
+        .enumerate()
+        .filter(|(_, (old, new))| old.amount > new.amount)
+        .map(|(tranche_id, _)| tranche_id.to_string())
+        .collect();
+    let data = WithdrawResponse {
+        depletion_order: order.clone(),
+        depleted_tranches: depleted_tranches(&before, &entry.dynamic_reward_log),
+        dynamic_reward_log: entry.dynamic_reward_log.clone(),
     };
+    Ok(response
+        .set_data(to_binary(&data)?)
+        .add_event(Event::new("ipr_withdraw")
+            .add_attribute("campaign_id", campaign.id.to_string())
+            .add_attribute("entry_address", address.to_string())
+            .add_attribute("sender", sender.to_string())
+            .add_attribute("amount", amount.to_string())
+            .add_attribute("denom", "uust")
+            .add_attribute("depletion_order", to_json_string(order)?)
+            .add_attribute("tranche_ids", tranche_ids.join(","))
+            .add_attribute("ust_amount", ust_amount.to_string())
+            .add_attribute("ust_deposited", entry.ust_deposited.to_string())))
+}
+
+pub fn try_claim(mut deps: DepsMut, info: MessageInfo, env: Env, campaign_id: u64, entry_address: String) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let valid_address = deps.api.addr_validate(&entry_address)?;
+    let time = env.block.time.seconds();
+    let mut response = Response::new();
 
-    if info.sender == entry_address || info.sender == state.owner {
-        convert_from_aust_and_make_withdraw(deps.as_ref(), info, amount)?;
-        ENTRIES.update(deps.storage, &valid_address, update_entry)?;
-        // TODO: remember to update state variables in all these functions
-        // STATE.update(deps.storage, action)
+    if info.sender == entry_address || info.sender == campaign.owner {
+        rewards_payable(&campaign)?;
+        // a referrer that never deposited still claims its referral bonus through an empty entry
+        let unclaimed_referral = referral::take_unclaimed(deps.storage, campaign.id, &valid_address)?;
+        let mut entry = match entries().may_load(deps.storage, campaign_key(campaign.id, &valid_address))? {
+            Some(entry) => entry,
+            None if !unclaimed_referral.is_zero() => empty_entry(time),
+            None => return Err(ContractError::CannotClaimWithoutDeposit {}),
+        };
+        response = response.add_events(settle(&mut deps, &mut campaign, &mut entry, &valid_address, time, true)?);
+        entry.claimable_reward = entry.claimable_reward.checked_add(unclaimed_referral)?;
+        if entry.claimable_reward == Uint128::zero() {
+            return Err(ContractError::Unauthorized {});
+        }
+        if campaign.reward_mode == RewardMode::Delegate {
+            // the reward contract pays out, claimable reward is zeroed once its reply confirms it. the
+            // reply runs in this same transaction, so one pending claim is all there can ever be
+            let pending = PendingClaim {
+                campaign_id,
+                entry_address: valid_address.clone(),
+                amount: entry.claimable_reward,
+            };
+            response = response.add_submessage(SubMsg::reply_on_success(
+                WasmMsg::Execute {
+                    contract_addr: campaign.reward_contract.to_string(),
+                    msg: to_binary(&RewardContractMsg::DistributeReward {
+                        recipient: valid_address.to_string(),
+                        amount: pending.amount,
+                    })?,
+                    funds: vec![],
+                },
+                CLAIM_REPLY_ID,
+            ));
+            PENDING_CLAIM.save(deps.storage, &pending)?;
+        } else {
+            // mint or transfer MIN from this contract to the entry's wallet
+            response = response
+                .add_message(reward_payout_msg(&campaign, &valid_address, entry.claimable_reward)?)
+                .add_event(claim_event(&campaign, &valid_address, entry.claimable_reward)
+                    .add_attribute("sender", info.sender.to_string()));
+            campaign.total_reward_claimed = campaign.total_reward_claimed.checked_add(entry.claimable_reward)?;
+            entry.claimable_reward = Uint128::zero();
+        }
+        save_entry(deps.storage, &mut campaign, &valid_address, &mut entry, time)?;
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
     } else {
         return Err(ContractError::Unauthorized {});
     }
-    Ok(Response::new().add_attribute("method", "try_withdraw"))
+    Ok(response.add_attribute("method", "try_claim"))
 }
 
-pub fn try_claim(deps: DepsMut, info: MessageInfo, entry_address: String) -> Result<Response, ContractError> {
-    let state = STATE.load(deps.storage)?;
+pub fn try_sell(deps: DepsMut, info: MessageInfo, env: Env, campaign_id: u64, entry_address: String, amount: Uint128, on_excess: Option<SellExcess>) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
     let valid_address = deps.api.addr_validate(&entry_address)?;
-    let update_entry = |entry: Option<Entry>| -> Result<Entry, ContractError> {
-        match entry {
-            Some(mut entry) => {
-                if entry.claimable_reward == Uint128::zero() {
-                    return Err(ContractError::Unauthorized {});
-                }
-                // transfer MIN from treasury wallet to users wallet
-                // will have to send MIN to this smart contract to give out
-                let mut response: Response = Default::default();
-                let coin_amount = coins(entry.claimable_reward.u128(), "umin");
-                response.messages = vec![SubMsg::new(BankMsg::Send {
-                    to_address: info.sender.to_string(),
-                    amount: coin_amount,
-                })];
-                entry.claimable_reward = Uint128::zero();
-                Ok(entry)
-            },
-            None => Err(ContractError::CannotClaimWithoutDeposit {}),
+    let time = env.block.time.seconds();
+    let mut response = Response::new();
+
+    if info.sender == entry_address || info.sender == campaign.owner {
+        // MIN is pulled from the entry's wallet under its allowance, UST goes back to the same wallet
+        rewards_payable(&campaign)?;
+        if amount.is_zero() {
+            return Err(ContractError::InvalidZeroAmount {});
         }
-    };
+        // whatever is over this epoch's caps or the reserve is rejected or left in the queue
+        let sellback_price = price::effective_price(deps.as_ref(), &env, &campaign)?;
+        let fill = sell_fill(deps.storage, &campaign, &valid_address, time, amount, sellback_price)?;
+        let excess = amount.checked_sub(fill)?;
+        if !excess.is_zero() {
+            if on_excess.unwrap_or(SellExcess::Reject) == SellExcess::Reject {
+                let available = limit::allowance(deps.storage, &campaign, &valid_address, time)?.available();
+                return Err(if matches!(available, Some(available) if available <= fill) {
+                    ContractError::SellLimitExceeded { available: fill }
+                } else {
+                    ContractError::InsufficientSellbackReserve { reserve: campaign.sellback_reserve }
+                });
+            }
+            if limit::queued(deps.storage, &campaign, &valid_address)?.sells >= limit::MAX_QUEUED_SELLS {
+                return Err(ContractError::TooManyQueuedSells { max: limit::MAX_QUEUED_SELLS });
+            }
+        }
+        response = response.add_message(WasmMsg::Execute {
+            contract_addr: campaign.reward_token.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: valid_address.to_string(),
+                recipient: env.contract.address.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        });
+        if !fill.is_zero() {
+            let (payout, event) = sell_payout(deps.as_ref(), &mut campaign, &valid_address, fill, sellback_price)?;
+            limit::record_sale(deps.storage, &campaign, &valid_address, time, fill)?;
+            response = response
+                .add_message(payout)
+                .add_event(event.add_attribute("sender", info.sender.to_string()));
+        }
+        if !excess.is_zero() {
+            let id = SELL_QUEUE_NEXT_ID.may_load(deps.storage)?.unwrap_or_default();
+            SELL_QUEUE.save(deps.storage, (U64Key::new(campaign.id), U64Key::new(id)), &QueuedSell {
+                entry_address: valid_address.clone(),
+                amount: excess,
+                time,
+                reward_token: campaign.reward_token.clone(),
+            })?;
+            SELL_QUEUE_NEXT_ID.save(deps.storage, &(id + 1))?;
+            limit::add_queued(deps.storage, &campaign, &valid_address, excess)?;
+            response = response.add_event(Event::new("ipr_sell_queued")
+                .add_attribute("campaign_id", campaign.id.to_string())
+                .add_attribute("entry_address", valid_address.to_string())
+                .add_attribute("sender", info.sender.to_string())
+                .add_attribute("queue_id", id.to_string())
+                .add_attribute("amount", excess.to_string()));
+        }
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+    } else {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(response.add_attribute("method", "try_sell"))
+}
+
+// fills queued sells oldest first, as far as the caps and the reserve allow. sells held back by their
+// address's cap are paged past, so up to `limit` sells fill per call; the scan stops at MAX_QUEUE_SCAN
+// entries and the last one looked at is returned to continue from
+pub fn try_process_sell_queue(deps: DepsMut, info: MessageInfo, env: Env, campaign_id: u64, start_after: Option<u64>, limit: Option<u32>) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let time = env.block.time.seconds();
+    let limit = limit.unwrap_or(DEFAULT_QUEUE_LIMIT).min(MAX_QUEUE_LIMIT) as usize;
+    rewards_payable(&campaign)?;
+    let sellback_price = price::effective_price(deps.as_ref(), &env, &campaign)?;
+    let start = start_after.map(|id| Bound::exclusive(U64Key::new(id)));
+    let mut response = Response::new();
+
+    let queued: StdResult<Vec<_>> = SELL_QUEUE.prefix(U64Key::new(campaign.id)).range(deps.storage, start, None, Order::Ascending).take(MAX_QUEUE_SCAN).collect();
+    let mut filled = 0;
+    let mut last_id = None;
+    for (key, mut sell) in queued? {
+        if filled == limit {
+            break;
+        }
+        let id = limit::queue_id(&key)?;
+        let allowance = limit::allowance(deps.storage, &campaign, &sell.entry_address, time)?;
+        // later sells can't be filled either once the global cap or the reserve runs out
+        if allowance.global_remaining == Some(Uint128::zero()) || reserve_fill(&campaign, sellback_price, sell.amount)?.is_zero() {
+            break;
+        }
+        last_id = Some(id);
+        let fill = sell_fill(deps.storage, &campaign, &sell.entry_address, time, sell.amount, sellback_price)?;
+        if fill.is_zero() {
+            continue;
+        }
+        let (payout, event) = sell_payout(deps.as_ref(), &mut campaign, &sell.entry_address, fill, sellback_price)?;
+        limit::record_sale(deps.storage, &campaign, &sell.entry_address, time, fill)?;
+        sell.amount = sell.amount.checked_sub(fill)?;
+        limit::remove_queued(deps.storage, &campaign, &sell.entry_address, fill, sell.amount.is_zero())?;
+        if sell.amount.is_zero() {
+            SELL_QUEUE.remove(deps.storage, (U64Key::new(campaign.id), U64Key::new(id)));
+        } else {
+            SELL_QUEUE.save(deps.storage, (U64Key::new(campaign.id), U64Key::new(id)), &sell)?;
+        }
+        filled += 1;
+        response = response
+            .add_message(payout)
+            .add_event(event
+                .add_attribute("sender", info.sender.to_string())
+                .add_attribute("queue_id", id.to_string()));
+    }
+    CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+    if let Some(id) = last_id {
+        response = response.add_attribute("last_queue_id", id.to_string());
+    }
+    Ok(response.add_attribute("method", "try_process_sell_queue"))
+}
+
+// hands back the MIN of a queued sell that hasn't been filled yet
+pub fn try_cancel_queued_sell(deps: DepsMut, info: MessageInfo, campaign_id: u64, id: u64) -> Result<Response, ContractError> {
+    let campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let sell = SELL_QUEUE.load(deps.storage, (U64Key::new(campaign.id), U64Key::new(id)))?;
+    if info.sender != sell.entry_address && info.sender != campaign.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    SELL_QUEUE.remove(deps.storage, (U64Key::new(campaign.id), U64Key::new(id)));
+    limit::remove_queued(deps.storage, &campaign, &sell.entry_address, sell.amount, true)?;
+    Ok(Response::new()
+        .add_attribute("method", "try_cancel_queued_sell")
+        .add_message(WasmMsg::Execute {
+            contract_addr: sell.reward_token.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: sell.entry_address.to_string(),
+                amount: sell.amount,
+            })?,
+            funds: vec![],
+        })
+        .add_event(Event::new("ipr_sell_cancelled")
+            .add_attribute("campaign_id", campaign.id.to_string())
+            .add_attribute("entry_address", sell.entry_address.to_string())
+            .add_attribute("sender", info.sender.to_string())
+            .add_attribute("queue_id", id.to_string())
+            .add_attribute("amount", sell.amount.to_string())
+            .add_attribute("reward_token", sell.reward_token.to_string())))
+}
+
+pub fn try_set_auto_compound(mut deps: DepsMut, info: MessageInfo, env: Env, campaign_id: u64, entry_address: String, enabled: bool) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let valid_address = deps.api.addr_validate(&entry_address)?;
+    let time = env.block.time.seconds();
+    let mut response = Response::new();
 
-    if info.sender == entry_address || info.sender == state.owner {
-        ENTRIES.update(deps.storage, &valid_address, update_entry)?;
+    if info.sender == entry_address || info.sender == campaign.owner {
+        let mut entry = entries().may_load(deps.storage, campaign_key(campaign.id, &valid_address))?
+            .ok_or(ContractError::CannotClaimWithoutDeposit {})?;
+        // what accrued under the old setting is settled under it
+        response = response.add_events(settle(&mut deps, &mut campaign, &mut entry, &valid_address, time, true)?);
+        entry.auto_compound = enabled;
+        save_entry(deps.storage, &mut campaign, &valid_address, &mut entry, time)?;
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+        response = response.add_event(Event::new("ipr_auto_compound")
+            .add_attribute("campaign_id", campaign.id.to_string())
+            .add_attribute("entry_address", valid_address.to_string())
+            .add_attribute("sender", info.sender.to_string())
+            .add_attribute("enabled", enabled.to_string()));
     } else {
         return Err(ContractError::Unauthorized {});
     }
-    Ok(Response::new().add_attribute("method", "try_claim"))
-}
-
-pub fn try_sell(deps: DepsMut, info: MessageInfo, env: Env, entry_address: String, amount: Uint128) -> Result<Response, ContractError> {
-    let state = STATE.load(deps.storage)?;
-    //let valid_address = deps.api.addr_validate(&entry_address)?;
-    
-    if info.sender == entry_address || info.sender == state.owner {
-        // TODO: make sure they have amount of MIN in wallet to sell, 
-        // Update here
-        // transfer UST from protocol to users wallet, 
-        let mut response: Response = Default::default();
-        let coin_amount = coins(amount.u128(), "uust");
-        response.messages = vec![SubMsg::new(BankMsg::Send {
-            to_address: entry_address,
-            amount: coin_amount,
-        })];
-        // transfer MIN from users wallet to protocol wallet,
-        let mut response: Response = Default::default();
-        let coin_amount = coins(amount.u128(), "umin");
-        response.messages = vec![SubMsg::new(BankMsg::Send {
-            to_address: env.contract.address.to_string(),
-            amount: coin_amount,
-        })];
+    Ok(response.add_attribute("method", "try_set_auto_compound"))
+}
+
+pub fn try_stake_reward(mut deps: DepsMut, info: MessageInfo, env: Env, campaign_id: u64, entry_address: String, amount: Uint128) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let valid_address = deps.api.addr_validate(&entry_address)?;
+    let time = env.block.time.seconds();
+    let mut response = Response::new();
+
+    if info.sender == entry_address || info.sender == campaign.owner {
+        if amount.is_zero() {
+            return Err(ContractError::InvalidZeroAmount {});
+        }
+        let mut entry = entries().may_load(deps.storage, campaign_key(campaign.id, &valid_address))?
+            .ok_or(ContractError::CannotClaimWithoutDeposit {})?;
+        response = response.add_events(settle(&mut deps, &mut campaign, &mut entry, &valid_address, time, true)?);
+        if amount > entry.claimable_reward {
+            return Err(ContractError::CannotStakeGreaterThanClaimable {});
+        }
+        entry.claimable_reward = entry.claimable_reward.checked_sub(amount)?;
+        entry.staked_reward = entry.staked_reward.checked_add(amount)?;
+        save_entry(deps.storage, &mut campaign, &valid_address, &mut entry, time)?;
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+        response = response.add_event(Event::new("ipr_stake")
+            .add_attribute("campaign_id", campaign.id.to_string())
+            .add_attribute("entry_address", valid_address.to_string())
+            .add_attribute("sender", info.sender.to_string())
+            .add_attribute("amount", amount.to_string())
+            .add_attribute("staked_reward", entry.staked_reward.to_string()));
     } else {
         return Err(ContractError::Unauthorized {});
     }
-    Ok(Response::new().add_attribute("method", "try_sell"))
+    Ok(response.add_attribute("method", "try_stake_reward"))
+}
+
+pub fn try_unstake_reward(mut deps: DepsMut, info: MessageInfo, env: Env, campaign_id: u64, entry_address: String, amount: Uint128) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let valid_address = deps.api.addr_validate(&entry_address)?;
+    let time = env.block.time.seconds();
+    let mut response = Response::new();
+
+    if info.sender == entry_address || info.sender == campaign.owner {
+        if amount.is_zero() {
+            return Err(ContractError::InvalidZeroAmount {});
+        }
+        let mut entry = entries().may_load(deps.storage, campaign_key(campaign.id, &valid_address))?
+            .ok_or(ContractError::CannotClaimWithoutDeposit {})?;
+        response = response.add_events(settle(&mut deps, &mut campaign, &mut entry, &valid_address, time, true)?);
+        if amount > entry.staked_reward {
+            return Err(ContractError::CannotUnstakeGreaterThanStaked {});
+        }
+        // unstaked MIN stops boosting straight away and becomes claimable after the cooldown
+        let release_time = time.saturating_add(campaign.unstake_cooldown);
+        entry.staked_reward = entry.staked_reward.checked_sub(amount)?;
+        entry.unbonding.push(Unbonding { amount, release_time });
+        save_entry(deps.storage, &mut campaign, &valid_address, &mut entry, time)?;
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+        response = response.add_event(Event::new("ipr_unstake")
+            .add_attribute("campaign_id", campaign.id.to_string())
+            .add_attribute("entry_address", valid_address.to_string())
+            .add_attribute("sender", info.sender.to_string())
+            .add_attribute("amount", amount.to_string())
+            .add_attribute("release_time", release_time.to_string())
+            .add_attribute("staked_reward", entry.staked_reward.to_string()));
+    } else {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(response.add_attribute("method", "try_unstake_reward"))
+}
+
+// moves principal to another address along with the tranches it came from, so it keeps its tier ages,
+// unclaimed reward stays behind
+pub fn try_transfer_entry(mut deps: DepsMut, info: MessageInfo, env: Env, campaign_id: u64, to: String, amount: Uint128) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let to_address = deps.api.addr_validate(&to)?;
+
+    // once receipts exist the principal has to move with them
+    if campaign.dp_token.is_some() {
+        return Err(ContractError::TransferWithReceiptToken {});
+    }
+    if amount.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    if to_address == info.sender {
+        return Err(ContractError::InvalidTransferRecipient {});
+    }
+    let events = move_position(&mut deps, &env, &mut campaign, &info.sender, &to_address, amount)?;
+    CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+    Ok(Response::new()
+        .add_attribute("method", "try_transfer_entry")
+        .add_events(events))
+}
+
+// called by the receipt token on every transfer, the principal moves with the receipt
+// and keeps its tier ages under its new holder
+pub fn try_receipt_transferred(mut deps: DepsMut, info: MessageInfo, env: Env, campaign_id: u64, from: String, to: String, amount: Uint128) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let from_address = deps.api.addr_validate(&from)?;
+    let to_address = deps.api.addr_validate(&to)?;
+
+    if campaign.dp_token.as_ref() != Some(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    // receipts sent here are being redeemed, the redeem hook takes the principal out instead
+    if amount.is_zero() || from_address == to_address || to_address == env.contract.address {
+        return Ok(Response::new().add_attribute("method", "try_receipt_transferred"));
+    }
+    let events = move_position(&mut deps, &env, &mut campaign, &from_address, &to_address, amount)?;
+    CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+    Ok(Response::new()
+        .add_attribute("method", "try_receipt_transferred")
+        .add_events(events))
+}
+
+// settles both entries and moves `amount` of principal from one to the other, tranche by tranche
+fn move_position(deps: &mut DepsMut, env: &Env, campaign: &mut Campaign, from: &Addr, to: &Addr, amount: Uint128) -> Result<Vec<Event>, ContractError> {
+    let time = env.block.time.seconds();
+    let mut events = vec![];
+
+    let mut from_entry = entries().may_load(deps.storage, campaign_key(campaign.id, from))?
+        .ok_or(ContractError::CannotWithdrawWithoutDeposit {})?;
+    events.extend(settle(deps, campaign, &mut from_entry, from, time, true)?);
+    let before = from_entry.dynamic_reward_log.clone();
+    let mut from_entry = some_withdraw_helper(from_entry, time, amount, &campaign.depletion_order)?;
+    let moved = depleted_tranches(&before, &from_entry.dynamic_reward_log);
+    if from_entry.ust_deposited.is_zero() {
+        remove_depositor(campaign)?;
+    }
+    save_entry(deps.storage, campaign, from, &mut from_entry, time)?;
+
+    let to_entry = match entries().may_load(deps.storage, campaign_key(campaign.id, to))? {
+        Some(mut entry) => {
+            events.extend(settle(deps, campaign, &mut entry, to, time, true)?);
+            entry
+        },
+        None => {
+            let mut entry = empty_entry(time);
+            partner::refresh_boost(deps.as_ref(), campaign.id, &mut entry, to)?;
+            entry
+        },
+    };
+    if to_entry.ust_deposited.is_zero() {
+        add_depositor(campaign)?;
+    }
+    let mut to_entry = some_transfer_helper(to_entry, &moved, amount, time)?;
+    save_entry(deps.storage, campaign, to, &mut to_entry, time)?;
+    snapshot_deposits(deps.storage, from, &from_entry, campaign, env.block.height)?;
+    snapshot_deposits(deps.storage, to, &to_entry, campaign, env.block.height)?;
+
+    let tranche_times: Vec<String> = moved.iter().map(|tranche| tranche.time.to_string()).collect();
+    events.push(Event::new("ipr_position_transfer")
+        .add_attribute("campaign_id", campaign.id.to_string())
+        .add_attribute("from", from.to_string())
+        .add_attribute("to", to.to_string())
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("tranche_times", tranche_times.join(",")));
+    Ok(events)
 }
 
-// TODO
-pub fn try_update_entries(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
-    let state = STATE.load(deps.storage)?;
-    // let entries: StdResult<Vec<_>> = ENTRIES.range(deps.storage, None, None, Order::Ascending).collect();
+// settles a page of entries in address order, the last address settled is where the next page starts after
+pub fn try_update_entries(mut deps: DepsMut, info: MessageInfo, env: Env, campaign_id: u64, start_after: Option<String>, limit: Option<u32>) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let time = env.block.time.seconds();
+    let limit = limit.unwrap_or(DEFAULT_ENTRIES_LIMIT).min(MAX_ENTRIES_LIMIT) as usize;
+    let mut response = Response::new();
 
-    if info.sender != state.owner {
+    if info.sender != campaign.owner {
         return Err(ContractError::Unauthorized {});
     } else {
-        // TODO: Update claimable reward, update average reward rate, update dynamic reward log
+        // move each tranche up to its current tier and add what it earned to claimable reward
+        let start = match start_after {
+            Some(address) => Some(Bound::exclusive(deps.api.addr_validate(&address)?.as_str())),
+            None => None,
+        };
+        let page: StdResult<Vec<_>> = entries().prefix(U64Key::new(campaign.id)).range(deps.storage, start, None, Order::Ascending).take(limit).collect();
+        let mut last_address = None;
+        for (key, mut entry) in page? {
+            let address = Addr::unchecked(String::from_utf8(key).map_err(StdError::from)?);
+            response = response.add_events(settle(&mut deps, &mut campaign, &mut entry, &address, time, true)?);
+            save_entry(deps.storage, &mut campaign, &address, &mut entry, time)?;
+            last_address = Some(address);
+        }
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+        if let Some(address) = last_address {
+            response = response.add_attribute("last_entry_address", address.to_string());
+        }
+    }
+    Ok(response.add_attribute("method", "try_update_state_and_entries"))
+}
 
-        // loop through the dynamic reward log in each entry
-        // check each entry against the time to determine if teir moves up
-        // calculate averaged reward 
-        // calculate how much MIN should be added to claimable reward
+pub fn try_cashout_yield(deps: DepsMut, info: MessageInfo, env: Env, campaign_id: u64) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let mut response = Response::new();
+    if info.sender != campaign.owner && info.sender != campaign.treasury_wallet {
+        return Err(ContractError::Unauthorized {});
+    } else {
+        if campaign.soft_cap.is_some() && campaign.status != CampaignStatus::Succeeded {
+            return Err(ContractError::YieldHeldUntilSuccess {});
+        }
+        // yield is whatever the campaign's aust is worth above the ust deposited into it
+        let epoch_state = anchor::epoch_state(deps.as_ref(), campaign.anc_market.clone(), env.block.height)?;
+        let pool_value = Uint256::from(campaign.aust_balance) * epoch_state.exchange_rate;
+        let ust_deposited = Uint256::from(campaign.ust_deposited);
+        if pool_value <= ust_deposited {
+            return Err(ContractError::NoYieldToCashout {});
+        }
+        let yield_amount = pool_value - ust_deposited;
+        let aust_amount = yield_amount / epoch_state.exchange_rate;
+
+        // redeemed ust is taxed leaving anchor and split between the recipients, the reserve share
+        // stays here and what is sent on is taxed again on the way out
+        let redeemed = anchor::deduct_tax(deps.as_ref(), coin(yield_amount.into(), "uust"))?;
+        response = response.add_messages(anchor::redeem_stable_msg(
+            deps.as_ref(),
+            campaign.anc_market.clone(),
+            campaign.aust_contract.clone(),
+            aust_amount.into(),
+        )?);
+        let mut event = Event::new("ipr_cashout_yield")
+            .add_attribute("campaign_id", campaign.id.to_string())
+            .add_attribute("amount", yield_amount.to_string())
+            .add_attribute("denom", "uust")
+            .add_attribute("aust_amount", aust_amount.to_string());
+        for (recipient, amount) in split::split_yield(&campaign.yield_split, redeemed.amount)? {
+            let payout = match &recipient {
+                YieldRecipient::Treasury {} => Some(BankMsg::Send {
+                    to_address: campaign.treasury_wallet.to_string(),
+                    amount: vec![anchor::deduct_tax(deps.as_ref(), coin(amount.u128(), "uust"))?],
+                }),
+                YieldRecipient::FeeCollector { address } => Some(BankMsg::Send {
+                    to_address: address.to_string(),
+                    amount: vec![anchor::deduct_tax(deps.as_ref(), coin(amount.u128(), "uust"))?],
+                }),
+                YieldRecipient::Burn {} => Some(BankMsg::Burn {
+                    amount: vec![coin(amount.u128(), "uust")],
+                }),
+                YieldRecipient::SellbackReserve {} => {
+                    campaign.sellback_reserve = campaign.sellback_reserve.checked_add(amount)?;
+                    None
+                },
+            };
+            if let (Some(payout), false) = (payout, amount.is_zero()) {
+                response = response.add_message(payout);
+            }
+            match &recipient {
+                YieldRecipient::Treasury {} => event = event.add_attribute("treasury_wallet", campaign.treasury_wallet.to_string()),
+                YieldRecipient::FeeCollector { address } => event = event.add_attribute("fee_collector", address.to_string()),
+                _ => {},
+            }
+            event = event.add_attribute(yield_attribute(&recipient), amount.to_string());
+        }
+        campaign.yield_cashed_out = campaign.yield_cashed_out.checked_add(to_uint128(yield_amount)?)?;
+        campaign.aust_balance = campaign.aust_balance.saturating_sub(to_uint128(aust_amount)?);
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+        response = response.add_event(event);
     }
-    Ok(Response::new().add_attribute("method", "try_update_state_and_entries"))
+    Ok(response.add_attribute("method", "try_cashout_yield"))
 }
 
-// TODO
-pub fn try_cashout_yield(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
-    let state = STATE.load(deps.storage)?;
-    if info.sender != state.owner {
+pub fn try_fund_rewards(deps: DepsMut, info: MessageInfo, env: Env, campaign_id: u64, amount: Uint128) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let mut response = Response::new();
+    if info.sender != campaign.owner {
         return Err(ContractError::Unauthorized {});
     } else {
-        // TODO
-        // get balance of aust
-        // yeild_amount = aust - ust_deposited 
-        // send yeild_amount to owner    
+        if amount.is_zero() {
+            return Err(ContractError::InvalidZeroAmount {});
+        }
+        // a transferred reward has to be held by this contract, a minted one only needs the ceiling raised
+        if campaign.reward_mode == RewardMode::Transfer {
+            response = response.add_message(WasmMsg::Execute {
+                contract_addr: campaign.reward_token.to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::TransferFrom {
+                    owner: info.sender.to_string(),
+                    recipient: env.contract.address.to_string(),
+                    amount,
+                })?,
+                funds: vec![],
+            });
+        }
+        campaign.reward_budget = campaign.reward_budget.checked_add(amount)?;
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+        response = response.add_event(Event::new("ipr_fund_rewards")
+            .add_attribute("campaign_id", campaign.id.to_string())
+            .add_attribute("sender", info.sender.to_string())
+            .add_attribute("amount", amount.to_string())
+            .add_attribute("reward_token", campaign.reward_token.to_string())
+            .add_attribute("reward_budget", campaign.reward_budget.to_string()));
     }
-    Ok(Response::new().add_attribute("method", "try_cashout_yield"))
+    Ok(response.add_attribute("method", "try_fund_rewards"))
 }
 
-pub fn try_set_treasury_wallet(deps: DepsMut, info: MessageInfo, address: String) -> Result<Response, ContractError> {
-    let mut state = STATE.load(deps.storage)?;   
-    if info.sender != state.owner {
+pub fn try_set_treasury_wallet(deps: DepsMut, info: MessageInfo, campaign_id: u64, address: String) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;   
+    let old_value = campaign.treasury_wallet.to_string();
+    if info.sender != campaign.owner {
         return Err(ContractError::Unauthorized {});
     } else {
         let valid_address = deps.api.addr_validate(&address)?;
-        state.treasury_wallet = valid_address;
-        STATE.save(deps.storage, &state)?;
+        campaign.treasury_wallet = valid_address;
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
     }
-    Ok(Response::new().add_attribute("method", "try_set_treasury_wallet"))
+    Ok(Response::new()
+        .add_attribute("method", "try_set_treasury_wallet")
+        .add_event(config_changed_event(campaign.id, "treasury_wallet", old_value, campaign.treasury_wallet)))
 }
 
-pub fn try_set_reward_contract(deps: DepsMut, info: MessageInfo, address: String) -> Result<Response, ContractError> {
-    let mut state = STATE.load(deps.storage)?;
-    if info.sender != state.owner {
+pub fn try_set_reward_contract(deps: DepsMut, info: MessageInfo, campaign_id: u64, address: String) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let old_value = campaign.reward_contract.to_string();
+    if info.sender != campaign.owner {
         return Err(ContractError::Unauthorized {});
     } else {
         let valid_address = deps.api.addr_validate(&address)?;
-        state.reward_contract = valid_address;
-        STATE.save(deps.storage, &state)?;
+        campaign.reward_contract = valid_address;
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+    }
+    Ok(Response::new()
+        .add_attribute("method", "try_set_reward_contract")
+        .add_event(config_changed_event(campaign.id, "reward_contract", old_value, campaign.reward_contract)))
+}
+
+pub fn try_set_reward_token(deps: DepsMut, info: MessageInfo, campaign_id: u64, address: String) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let old_value = campaign.reward_token.to_string();
+    if info.sender != campaign.owner {
+        return Err(ContractError::Unauthorized {});
+    } else {
+        let valid_address = deps.api.addr_validate(&address)?;
+        campaign.reward_token = valid_address;
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+    }
+    Ok(Response::new()
+        .add_attribute("method", "try_set_reward_token")
+        .add_event(config_changed_event(campaign.id, "reward_token", old_value, campaign.reward_token)))
+}
+
+pub fn try_set_reward_mode(deps: DepsMut, info: MessageInfo, campaign_id: u64, mode: RewardMode) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let old_value = reward_mode_name(&campaign.reward_mode);
+    if info.sender != campaign.owner {
+        return Err(ContractError::Unauthorized {});
+    } else {
+        campaign.reward_mode = mode;
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
     }
-    Ok(Response::new().add_attribute("method", "try_set_reward_contract"))
+    Ok(Response::new()
+        .add_attribute("method", "try_set_reward_mode")
+        .add_event(config_changed_event(campaign.id, "reward_mode", old_value, reward_mode_name(&campaign.reward_mode))))
 }
 
-pub fn try_set_tier_data(deps: DepsMut, info: MessageInfo, data: (u8, u64, u64)) -> Result<Response, ContractError> {
-    let mut state = STATE.load(deps.storage)?;
-    if info.sender != state.owner {
+pub fn try_set_tier_data(deps: DepsMut, info: MessageInfo, env: Env, campaign_id: u64, data: (u8, Decimal256, u64)) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let old_value = reward::tier_schedule(&campaign);
+    let time = env.block.time.seconds();
+    if info.sender != campaign.owner {
         return Err(ContractError::Unauthorized {});
     } else {
+        // what was earned up to now stays at the old schedule, entries settle across the checkpoint
+        reward::advance_outstanding(&mut campaign, time)?;
+        if campaign.tier_history.last().is_none_or(|checkpoint| checkpoint.until < time) {
+            campaign.tier_history.push(TierCheckpoint { schedule: old_value, until: time });
+        }
         if data.0 == 0 {
-            state.tier0rate = data.1;
-            state.tier0time = data.2;
+            campaign.tier0rate = data.1;
+            campaign.tier0time = data.2;
         } else if data.0 == 1 {
-            state.tier1rate = data.1;
-            state.tier1time = data.2;
+            campaign.tier1rate = data.1;
+            campaign.tier1time = data.2;
         } else if data.0 == 2 {
-            state.tier2rate = data.1;
-            state.tier2time = data.2;
+            campaign.tier2rate = data.1;
+            campaign.tier2time = data.2;
         } else if data.0 == 3 {
-            state.tier3rate = data.1;
-            state.tier3time = data.2;
+            campaign.tier3rate = data.1;
+            campaign.tier3time = data.2;
         } else {
             return Err(ContractError::Unauthorized {});
         }
-        STATE.save(deps.storage, &state)?;
+        reward::validate_tier_schedule(&reward::tier_schedule(&campaign))?;
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
     }
-    Ok(Response::new().add_attribute("method", "try_set_tier_data"))
+    let (old_rate, old_time) = old_value[data.0 as usize];
+    Ok(Response::new()
+        .add_attribute("method", "try_set_tier_data")
+        .add_event(config_changed_event(campaign.id, &format!("tier{}rate", data.0), old_rate, data.1))
+        .add_event(config_changed_event(campaign.id, &format!("tier{}time", data.0), old_time, data.2)))
 }
 
-pub fn try_set_anc_market(deps: DepsMut, info: MessageInfo, address: Addr) -> Result<Response, ContractError> {
-    let mut state = STATE.load(deps.storage)?;
-    if info.sender != state.owner {
+pub fn try_set_anc_market(deps: DepsMut, info: MessageInfo, campaign_id: u64, address: Addr) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let old_value = campaign.anc_market.clone();
+    if info.sender != campaign.owner {
         return Err(ContractError::Unauthorized {});
     } else {
-        state.anc_market = address.to_string();
-        STATE.save(deps.storage, &state)?;
+        campaign.anc_market = address.to_string();
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
     }
-    Ok(Response::new().add_attribute("method", "try_set_tier_data"))
+    Ok(Response::new()
+        .add_attribute("method", "try_set_anc_market")
+        .add_event(config_changed_event(campaign.id, "anc_market", old_value, campaign.anc_market)))
+}
+
+pub fn try_set_sellback_price(deps: DepsMut, info: MessageInfo, campaign_id: u64, price: Decimal256) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let old_value = campaign.sellback_price;
+    if info.sender != campaign.owner {
+        return Err(ContractError::Unauthorized {});
+    } else {
+        price::validate_sellback_price(price)?;
+        campaign.sellback_price = price;
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+    }
+    Ok(Response::new()
+        .add_attribute("method", "try_set_sellback_price")
+        .add_event(config_changed_event(campaign.id, "sellback_price", old_value, price)))
+}
+
+pub fn try_set_sellback_source(deps: DepsMut, info: MessageInfo, campaign_id: u64, source: PriceSource, floor: Decimal256, ceiling: Option<Decimal256>) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let old_campaign = campaign.clone();
+    if info.sender != campaign.owner {
+        return Err(ContractError::Unauthorized {});
+    } else {
+        price::validate_sellback_source(&source, floor, ceiling)?;
+        campaign.sellback_source = match source {
+            PriceSource::Static {} => PriceSource::Static {},
+            PriceSource::Pair { pair } => PriceSource::Pair { pair: deps.api.addr_validate(pair.as_str())? },
+            PriceSource::Twap { pair, window, max_staleness } => PriceSource::Twap {
+                pair: deps.api.addr_validate(pair.as_str())?,
+                window,
+                max_staleness,
+            },
+        };
+        campaign.sellback_floor = floor;
+        campaign.sellback_ceiling = ceiling;
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+        // observations of a previous pair say nothing about the new one
+        if campaign.sellback_source != old_campaign.sellback_source {
+            PRICE_OBSERVATIONS.remove(deps.storage, U64Key::new(campaign.id));
+        }
+    }
+    Ok(Response::new()
+        .add_attribute("method", "try_set_sellback_source")
+        .add_event(config_changed_event(
+            campaign.id,
+            "sellback_source",
+            to_json_string(&old_campaign.sellback_source)?,
+            to_json_string(&campaign.sellback_source)?,
+        ))
+        .add_event(config_changed_event(campaign.id, "sellback_floor", old_campaign.sellback_floor, floor))
+        .add_event(config_changed_event(
+            campaign.id,
+            "sellback_ceiling",
+            to_json_string(&old_campaign.sellback_ceiling)?,
+            to_json_string(&ceiling)?,
+        )))
+}
+
+pub fn try_set_yield_split(deps: DepsMut, info: MessageInfo, campaign_id: u64, yield_split: Vec<YieldShare>) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let old_value = campaign.yield_split.clone();
+    if info.sender != campaign.owner {
+        return Err(ContractError::Unauthorized {});
+    } else {
+        campaign.yield_split = split::validated_yield_split(deps.api, yield_split)?;
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+    }
+    Ok(Response::new()
+        .add_attribute("method", "try_set_yield_split")
+        .add_event(config_changed_event(
+            campaign.id,
+            "yield_split",
+            to_json_string(&old_value)?,
+            to_json_string(&campaign.yield_split)?,
+        )))
+}
+
+pub fn try_set_sell_limits(deps: DepsMut, info: MessageInfo, campaign_id: u64, epoch_length: u64, global_cap: Option<Uint128>, address_cap: Option<Uint128>) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let old_campaign = campaign.clone();
+    if info.sender != campaign.owner {
+        return Err(ContractError::Unauthorized {});
+    } else {
+        if epoch_length == 0 {
+            return Err(ContractError::InvalidSellEpoch {});
+        }
+        campaign.sell_epoch_length = epoch_length;
+        campaign.sell_global_cap = global_cap;
+        campaign.sell_address_cap = address_cap;
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+    }
+    Ok(Response::new()
+        .add_attribute("method", "try_set_sell_limits")
+        .add_event(config_changed_event(campaign.id, "sell_epoch_length", old_campaign.sell_epoch_length, epoch_length))
+        .add_event(config_changed_event(
+            campaign.id,
+            "sell_global_cap",
+            to_json_string(&old_campaign.sell_global_cap)?,
+            to_json_string(&global_cap)?,
+        ))
+        .add_event(config_changed_event(
+            campaign.id,
+            "sell_address_cap",
+            to_json_string(&old_campaign.sell_address_cap)?,
+            to_json_string(&address_cap)?,
+        )))
+}
+
+pub fn try_set_referral_terms(deps: DepsMut, info: MessageInfo, campaign_id: u64, share: Decimal256, period: u64) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let old_campaign = campaign.clone();
+    if info.sender != campaign.owner {
+        return Err(ContractError::Unauthorized {});
+    } else {
+        if share > Decimal256::one() {
+            return Err(ContractError::InvalidReferralShare {});
+        }
+        campaign.referral_share = share;
+        campaign.referral_period = period;
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+    }
+    Ok(Response::new()
+        .add_attribute("method", "try_set_referral_terms")
+        .add_event(config_changed_event(campaign.id, "referral_share", old_campaign.referral_share, share))
+        .add_event(config_changed_event(campaign.id, "referral_period", old_campaign.referral_period, period)))
+}
+
+pub fn try_set_stake_terms(deps: DepsMut, info: MessageInfo, campaign_id: u64, boost_rate: Decimal256, boost_cap: Decimal256, unstake_cooldown: u64) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let old_campaign = campaign.clone();
+    if info.sender != campaign.owner {
+        return Err(ContractError::Unauthorized {});
+    } else {
+        if boost_cap < boost_rate || boost_cap > Decimal256::percent(MAX_STAKE_BOOST_PERCENT) || unstake_cooldown > MAX_UNSTAKE_COOLDOWN {
+            return Err(ContractError::InvalidStakeTerms {});
+        }
+        campaign.stake_boost_rate = boost_rate;
+        campaign.stake_boost_cap = boost_cap;
+        campaign.unstake_cooldown = unstake_cooldown;
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+    }
+    Ok(Response::new()
+        .add_attribute("method", "try_set_stake_terms")
+        .add_event(config_changed_event(campaign.id, "stake_boost_rate", old_campaign.stake_boost_rate, boost_rate))
+        .add_event(config_changed_event(campaign.id, "stake_boost_cap", old_campaign.stake_boost_cap, boost_cap))
+        .add_event(config_changed_event(campaign.id, "unstake_cooldown", old_campaign.unstake_cooldown, unstake_cooldown)))
+}
+
+pub fn try_set_partner_boost(deps: DepsMut, info: MessageInfo, campaign_id: u64, contract: String, kind: PartnerKind, brackets: Vec<BoostBracket>) -> Result<Response, ContractError> {
+    let campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let contract = deps.api.addr_validate(&contract)?;
+    let old_value = PARTNER_BOOSTS.may_load(deps.storage, campaign_key(campaign.id, &contract))?;
+    if info.sender != campaign.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    partner::validate_brackets(&brackets)?;
+    let partner = PartnerBoost { kind, brackets };
+    PARTNER_BOOSTS.save(deps.storage, campaign_key(campaign.id, &contract), &partner)?;
+    Ok(Response::new()
+        .add_attribute("method", "try_set_partner_boost")
+        .add_event(config_changed_event(
+            campaign.id,
+            &format!("partner_boost.{}", contract),
+            to_json_string(&old_value)?,
+            to_json_string(&Some(partner))?,
+        )))
+}
+
+pub fn try_remove_partner_boost(deps: DepsMut, info: MessageInfo, campaign_id: u64, contract: String) -> Result<Response, ContractError> {
+    let campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let contract = deps.api.addr_validate(&contract)?;
+    let old_value = PARTNER_BOOSTS.may_load(deps.storage, campaign_key(campaign.id, &contract))?;
+    if info.sender != campaign.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    PARTNER_BOOSTS.remove(deps.storage, campaign_key(campaign.id, &contract));
+    Ok(Response::new()
+        .add_attribute("method", "try_remove_partner_boost")
+        .add_event(config_changed_event(
+            campaign.id,
+            &format!("partner_boost.{}", contract),
+            to_json_string(&old_value)?,
+            to_json_string(&None::<PartnerBoost>)?,
+        )))
+}
+
+pub fn try_set_depletion_order(deps: DepsMut, info: MessageInfo, campaign_id: u64, order: DepletionOrder) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let old_value = campaign.depletion_order.clone();
+    if info.sender != campaign.owner {
+        return Err(ContractError::Unauthorized {});
+    } else {
+        campaign.depletion_order = order;
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+    }
+    Ok(Response::new()
+        .add_attribute("method", "try_set_depletion_order")
+        .add_event(config_changed_event(
+            campaign.id,
+            "depletion_order",
+            to_json_string(&old_value)?,
+            to_json_string(&campaign.depletion_order)?,
+        )))
+}
+
+pub fn try_set_campaign_window(deps: DepsMut, info: MessageInfo, campaign_id: u64, start_time: u64, end_time: Option<u64>) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let old_campaign = campaign.clone();
+    if info.sender != campaign.owner {
+        return Err(ContractError::Unauthorized {});
+    } else {
+        validate_campaign_window(start_time, end_time)?;
+        validate_success_criteria(start_time, campaign.soft_cap, campaign.deadline)?;
+        campaign.start_time = start_time;
+        campaign.end_time = end_time;
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+    }
+    Ok(Response::new()
+        .add_attribute("method", "try_set_campaign_window")
+        .add_event(config_changed_event(campaign.id, "start_time", old_campaign.start_time, start_time))
+        .add_event(config_changed_event(
+            campaign.id,
+            "end_time",
+            to_json_string(&old_campaign.end_time)?,
+            to_json_string(&end_time)?,
+        )))
+}
+
+pub fn try_set_deposit_cap(deps: DepsMut, info: MessageInfo, campaign_id: u64, cap: Option<Uint128>) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let old_value = campaign.deposit_cap;
+    if info.sender != campaign.owner {
+        return Err(ContractError::Unauthorized {});
+    } else {
+        campaign.deposit_cap = cap;
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+    }
+    Ok(Response::new()
+        .add_attribute("method", "try_set_deposit_cap")
+        .add_event(config_changed_event(
+            campaign.id,
+            "deposit_cap",
+            to_json_string(&old_value)?,
+            to_json_string(&cap)?,
+        )))
+}
+
+// the target can be moved until the deadline it was set against has passed
+pub fn try_set_success_criteria(deps: DepsMut, info: MessageInfo, env: Env, campaign_id: u64, soft_cap: Option<Uint128>, deadline: Option<u64>) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let old_campaign = campaign.clone();
+    if info.sender != campaign.owner {
+        return Err(ContractError::Unauthorized {});
+    } else {
+        if campaign.status != CampaignStatus::Active || matches!(campaign.deadline, Some(deadline) if env.block.time.seconds() >= deadline) {
+            return Err(ContractError::CampaignFinalized {});
+        }
+        validate_success_criteria(campaign.start_time, soft_cap, deadline)?;
+        // a soft cap is only ever measured against deposits made under it, so it can't be added to a
+        // campaign that already counts principal, seeded or deposited
+        if campaign.soft_cap.is_none() && soft_cap.is_some() && !campaign.ust_deposited.is_zero() {
+            return Err(ContractError::SoftCapWithDeposits {});
+        }
+        campaign.soft_cap = soft_cap;
+        campaign.deadline = deadline;
+        CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+    }
+    Ok(Response::new()
+        .add_attribute("method", "try_set_success_criteria")
+        .add_event(config_changed_event(
+            campaign.id,
+            "soft_cap",
+            to_json_string(&old_campaign.soft_cap)?,
+            to_json_string(&soft_cap)?,
+        ))
+        .add_event(config_changed_event(
+            campaign.id,
+            "deadline",
+            to_json_string(&old_campaign.deadline)?,
+            to_json_string(&deadline)?,
+        )))
+}
+
+// a campaign that took in its soft cap by the deadline succeeded, any other has failed
+pub fn try_finalize(deps: DepsMut, info: MessageInfo, env: Env, campaign_id: u64) -> Result<Response, ContractError> {
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let (soft_cap, deadline) = match (campaign.soft_cap, campaign.deadline) {
+        (Some(soft_cap), Some(deadline)) => (soft_cap, deadline),
+        _ => return Err(ContractError::NoSuccessCriteria {}),
+    };
+    if campaign.status != CampaignStatus::Active {
+        return Err(ContractError::CampaignFinalized {});
+    }
+    if env.block.time.seconds() < deadline {
+        return Err(ContractError::DeadlineNotReached { deadline });
+    }
+    campaign.status = if campaign.ust_deposited >= soft_cap {
+        CampaignStatus::Succeeded
+    } else {
+        CampaignStatus::Failed
+    };
+    CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+    Ok(Response::new()
+        .add_attribute("method", "try_finalize")
+        .add_event(Event::new("ipr_finalize")
+            .add_attribute("campaign_id", campaign.id.to_string())
+            .add_attribute("sender", info.sender.to_string())
+            .add_attribute("status", to_json_string(&campaign.status)?)
+            .add_attribute("soft_cap", soft_cap.to_string())
+            .add_attribute("ust_deposited", campaign.ust_deposited.to_string())
+            .add_attribute("aust_balance", campaign.aust_balance.to_string())))
+}
+
+pub fn try_record_sellback_price(deps: DepsMut, info: MessageInfo, env: Env, campaign_id: u64) -> Result<Response, ContractError> {
+    let campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    // a sample holds until the next one, so only the owner can take them
+    if info.sender != campaign.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    let observation = price::record_observation(deps, &env, &campaign)?;
+    Ok(Response::new()
+        .add_attribute("method", "try_record_sellback_price")
+        .add_event(Event::new("ipr_sellback_price_recorded")
+            .add_attribute("campaign_id", campaign.id.to_string())
+            .add_attribute("time", observation.time.to_string())
+            .add_attribute("price", observation.price.to_string())))
 }
 
 // helpers
-fn some_deposit_helper(mut entry: Entry, amount: Uint128, time: u64) -> StdResult<Entry> {
-    entry.ust_deposited += amount;
+pub fn to_json_string<T: Serialize>(value: &T) -> StdResult<String> {
+    String::from_utf8(to_vec(value)?).map_err(StdError::from)
+}
+
+pub fn validate_campaign_window(start_time: u64, end_time: Option<u64>) -> Result<(), ContractError> {
+    if matches!(end_time, Some(end_time) if end_time <= start_time) {
+        return Err(ContractError::InvalidCampaignWindow {});
+    }
+    Ok(())
+}
+
+// a soft cap comes with a deadline after the start to finalize against, and the other way round
+pub fn validate_success_criteria(start_time: u64, soft_cap: Option<Uint128>, deadline: Option<u64>) -> Result<(), ContractError> {
+    match (soft_cap, deadline) {
+        (None, None) => Ok(()),
+        (Some(_), Some(deadline)) if deadline > start_time => Ok(()),
+        _ => Err(ContractError::InvalidSuccessCriteria {}),
+    }
+}
+
+// deposits close at the end of the window, at the deadline until the campaign has succeeded, and for good once it fails
+fn taking_deposits(campaign: &Campaign, time: u64) -> bool {
+    let open = time >= campaign.start_time && !matches!(campaign.end_time, Some(end_time) if time >= end_time);
+    match campaign.status {
+        CampaignStatus::Active => open && !matches!(campaign.deadline, Some(deadline) if time >= deadline),
+        CampaignStatus::Succeeded => open,
+        CampaignStatus::Failed => false,
+    }
+}
+
+// a campaign with a soft cap pays no rewards out until it has succeeded, and none at all once it has failed
+pub fn rewards_payable(campaign: &Campaign) -> Result<(), ContractError> {
+    match campaign.status {
+        CampaignStatus::Failed => Err(ContractError::RewardsVoided {}),
+        CampaignStatus::Active if campaign.soft_cap.is_some() => Err(ContractError::RewardsHeldUntilSuccess {}),
+        _ => Ok(()),
+    }
+}
+
+// the aUST a failed campaign hands back for `amount` of principal, its share of the campaign's aUST
+pub fn refund_aust(campaign: &Campaign, amount: Uint128) -> Uint256 {
+    if campaign.ust_deposited.is_zero() {
+        return Uint256::zero();
+    }
+    Uint256::from(campaign.aust_balance) * ratio(amount, campaign.ust_deposited)
+}
+
+// how much of a sell fills right now, within this epoch's caps and what the reserve can pay for
+pub fn sell_fill(storage: &dyn Storage, campaign: &Campaign, address: &Addr, time: u64, amount: Uint128, sellback_price: Decimal256) -> StdResult<Uint128> {
+    let available = limit::allowance(storage, campaign, address, time)?.available();
+    Ok(available.unwrap_or(amount).min(amount).min(reserve_fill(campaign, sellback_price, amount)?))
+}
+
+// MIN the reserve can still pay for at the sellback price
+fn reserve_fill(campaign: &Campaign, sellback_price: Decimal256, amount: Uint128) -> StdResult<Uint128> {
+    if sellback_price.is_zero() {
+        return Ok(amount);
+    }
+    to_uint128(Uint256::from(campaign.sellback_reserve) / sellback_price)
+}
+
+// pays for MIN sold back out of the reserve
+fn sell_payout(deps: Deps, campaign: &mut Campaign, address: &Addr, amount: Uint128, sellback_price: Decimal256) -> Result<(BankMsg, Event), ContractError> {
+    let ust_amount = price::sellback_amount(sellback_price, amount)?;
+    // sellbacks are only paid out of the reserve the yield has built up
+    if ust_amount > campaign.sellback_reserve {
+        return Err(ContractError::InsufficientSellbackReserve { reserve: campaign.sellback_reserve });
+    }
+    campaign.total_reward_bought_back = campaign.total_reward_bought_back.checked_add(amount)?;
+    campaign.sellback_reserve = campaign.sellback_reserve.checked_sub(ust_amount)?;
+    let payout = BankMsg::Send {
+        to_address: address.to_string(),
+        amount: vec![anchor::deduct_tax(deps, coin(ust_amount.u128(), "uust"))?],
+    };
+    let event = Event::new("ipr_sell")
+        .add_attribute("campaign_id", campaign.id.to_string())
+        .add_attribute("entry_address", address.to_string())
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("reward_token", campaign.reward_token.to_string())
+        .add_attribute("sellback_price", sellback_price.to_string())
+        .add_attribute("ust_amount", ust_amount.to_string());
+    Ok((payout, event))
+}
+
+// settles an entry into the running campaign totals at the campaign's throttle ratio, reporting each
+// tier promotion as an event. the time since the last settle accrues at the lower of the partner boost
+// stored then and the one held now, which only applies in full from here on. without `query_partners`
+// the holdings count as gone, so a withdrawal never depends on a partner contract
+fn settle(deps: &mut DepsMut, campaign: &mut Campaign, entry: &mut Entry, address: &Addr, time: u64, query_partners: bool) -> StdResult<Vec<Event>> {
+    let current = if query_partners {
+        partner::current_boost(deps.as_ref(), campaign.id, address)?
+    } else {
+        Decimal256::zero()
+    };
+    entry.partner_boost = partner::settled_boost(entry, current);
+    reward::advance_outstanding(campaign, time)?;
+    let referral_bonus = referral::pending_bonus(deps.storage, campaign, entry, address, entry.last_reward_time, time)?;
+    let ratio = reward::throttle_ratio(campaign, entry, referral_bonus, time)?;
+    let events = settle_with_ratio(deps.storage, campaign, entry, address, time, ratio)?;
+    entry.partner_boost = current;
+    Ok(events)
+}
+
+fn settle_with_ratio(storage: &mut dyn Storage, campaign: &mut Campaign, entry: &mut Entry, address: &Addr, time: u64, ratio: Decimal256) -> StdResult<Vec<Event>> {
+    // nothing accrues once a campaign has failed
+    if campaign.status == CampaignStatus::Failed {
+        return Ok(vec![]);
+    }
+    // what the entry was counted for in the outstanding estimate is settled now
+    campaign.outstanding_reward = campaign.outstanding_reward.saturating_sub(reward::estimated_reward(campaign, entry, time)?);
+    let from = entry.last_reward_time;
+    let settlement = reward::settle_entry(campaign, entry, time, ratio)?;
+    campaign.total_reward_accrued = campaign.total_reward_accrued.checked_add(settlement.accrued)?;
+    let mut events: Vec<Event> = settlement.promotions
+        .into_iter()
+        .map(|promotion| Event::new("ipr_tier_promoted")
+            .add_attribute("campaign_id", campaign.id.to_string())
+            .add_attribute("entry_address", address.to_string())
+            .add_attribute("tranche_id", promotion.tranche_id.to_string())
+            .add_attribute("old_tier", promotion.old_tier.to_string())
+            .add_attribute("new_tier", promotion.new_tier.to_string()))
+        .collect();
+    events.extend(referral::credit_bonus(storage, campaign, entry, address, from, time, ratio)?);
+    Ok(events)
+}
+
+pub fn config_changed_event(campaign_id: u64, key: &str, old_value: impl ToString, new_value: impl ToString) -> Event {
+    Event::new("ipr_config_changed")
+        .add_attribute("campaign_id", campaign_id.to_string())
+        .add_attribute("key", key)
+        .add_attribute("old_value", old_value.to_string())
+        .add_attribute("new_value", new_value.to_string())
+}
+
+fn reward_payout_msg(campaign: &Campaign, recipient: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
+    let msg = match campaign.reward_mode {
+        RewardMode::Mint => Cw20ExecuteMsg::Mint { recipient: recipient.to_string(), amount },
+        _ => Cw20ExecuteMsg::Transfer { recipient: recipient.to_string(), amount },
+    };
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: campaign.reward_token.to_string(),
+        msg: to_binary(&msg)?,
+        funds: vec![],
+    }))
+}
+
+pub fn claim_event(campaign: &Campaign, entry_address: &Addr, amount: Uint128) -> Event {
+    Event::new("ipr_claim")
+        .add_attribute("campaign_id", campaign.id.to_string())
+        .add_attribute("entry_address", entry_address.to_string())
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("reward_token", campaign.reward_token.to_string())
+        .add_attribute("reward_mode", reward_mode_name(&campaign.reward_mode))
+}
+
+fn yield_attribute(recipient: &YieldRecipient) -> &'static str {
+    match recipient {
+        YieldRecipient::Treasury {} => "treasury_amount",
+        YieldRecipient::SellbackReserve {} => "reserve_amount",
+        YieldRecipient::FeeCollector { .. } => "fee_amount",
+        YieldRecipient::Burn {} => "burn_amount",
+    }
+}
+
+fn reward_mode_name(mode: &RewardMode) -> &'static str {
+    match mode {
+        RewardMode::Mint => "mint",
+        RewardMode::Transfer => "transfer",
+        RewardMode::Delegate => "delegate",
+    }
+}
+
+// stores the entry with its averaged rate and its part of the campaign's reward rate as of `time`. the
+// stake boost is checkpointed here too, stake and term changes only apply from here on
+fn save_entry(storage: &mut dyn Storage, campaign: &mut Campaign, address: &Addr, entry: &mut Entry, time: u64) -> StdResult<()> {
+    reward::advance_outstanding(campaign, time)?;
+    entry.stake_boost = reward::stake_boost(campaign, entry);
+    reward::refresh_averaged_rate(campaign, entry, time);
+    let referral_share = referral::active_share(storage, campaign, address, time)?;
+    reward::track_rate(campaign, entry, referral_share, time);
+    entries().save(storage, campaign_key(campaign.id, address), entry)
+}
+
+fn add_depositor(campaign: &mut Campaign) -> StdResult<()> {
+    campaign.depositor_count = campaign.depositor_count
+        .checked_add(1)
+        .ok_or_else(|| OverflowError::new(OverflowOperation::Add, campaign.depositor_count, 1))?;
+    Ok(())
+}
+
+// an entry drawn down to zero stops counting as a depositor, it was counted when it went above zero
+fn remove_depositor(campaign: &mut Campaign) -> StdResult<()> {
+    campaign.depositor_count = campaign.depositor_count
+        .checked_sub(1)
+        .ok_or_else(|| OverflowError::new(OverflowOperation::Sub, campaign.depositor_count, 1))?;
+    Ok(())
+}
+
+fn sent_amount(info: &MessageInfo, denom: &str) -> Uint128 {
+    info.funds
+        .iter()
+        .filter(|coin| coin.denom == denom)
+        .map(|coin| coin.amount)
+        .sum()
+}
+
+fn snapshot_deposits(storage: &mut dyn Storage, address: &Addr, entry: &Entry, campaign: &Campaign, height: u64) -> StdResult<()> {
+    DEPOSITED.save(storage, campaign_key(campaign.id, address), &entry.ust_deposited, height)?;
+    TOTAL_DEPOSITED.save(storage, U64Key::new(campaign.id), &campaign.ust_deposited, height)
+}
+
+pub fn some_deposit_helper(mut entry: Entry, amount: Uint128, time: u64) -> StdResult<Entry> {
+    entry.ust_deposited = entry.ust_deposited.checked_add(amount)?;
     let deposit = Deposit {
-        amount: amount,
-        time: time,
+        amount,
+        time,
     };
     entry.ust_deposit_log.push(deposit);
     let reward = Reward {
-        amount: amount,
-        time: time,
+        amount,
+        time,
         reward_tier: 0,
     };
     entry.dynamic_reward_log.push(reward);
     Ok(entry)
 }
 
-fn none_deposit_helper(amount: Uint128, time: u64) -> StdResult<Entry> {
+pub fn none_deposit_helper(amount: Uint128, time: u64) -> StdResult<Entry> {
     let deposit = Deposit {
-        amount: amount,
-        time: time,
+        amount,
+        time,
     };
     let reward = Reward {
-        amount: amount,
-        time: time,
+        amount,
+        time,
         reward_tier: 0,
     };
     let entry = Entry {
         claimable_reward: Uint128::zero(), 
         ust_deposited: amount, 
-        averaged_reward_rate: 0,
+        averaged_reward_rate: Decimal256::zero(),
         ust_deposit_log: vec![deposit], 
         ust_withdraw_log: vec![], 
-        dynamic_reward_log: vec![reward] 
+        dynamic_reward_log: vec![reward],
+        last_reward_time: time,
+        time_weighted_deposit: Uint128::zero(),
+        reward_accrued: Uint128::zero(),
+        auto_compound: false,
+        staked_reward: Uint128::zero(),
+        unbonding: vec![],
+        stake_boost: Decimal256::one(),
+        partner_boost: Decimal256::zero(),
+        tier_principal: [Decimal256::zero(); 4],
+        tier_index: [Decimal256::zero(); 4],
     };
     Ok(entry)
 }
 
-fn make_deposit_and_convert_to_aust(deps: Deps, env: Env, amount: Uint128) -> Result<Response, ContractError> {
-    let state = STATE.load(deps.storage)?;
-    // transfer funds from user to protocol
-    let mut response: Response = Default::default();
-    let coin_amount = coins(amount.u128(), "uust");
-    // I think including cw20 has messed with dependencies bringing in newest alpha version of cosmwasm_std
-    response.messages = vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: env.contract.address.to_string(),
-        msg: to_binary(&Cw20ExecuteMsg::Send {amount: amount, contract:env.contract.address.to_string(), msg: to_binary("data")?})?,
-        funds: coin_amount,
-    }))];
+fn empty_entry(time: u64) -> Entry {
+    Entry {
+        claimable_reward: Uint128::zero(),
+        ust_deposited: Uint128::zero(),
+        averaged_reward_rate: Decimal256::zero(),
+        ust_deposit_log: vec![],
+        ust_withdraw_log: vec![],
+        dynamic_reward_log: vec![],
+        last_reward_time: time,
+        time_weighted_deposit: Uint128::zero(),
+        reward_accrued: Uint128::zero(),
+        auto_compound: false,
+        staked_reward: Uint128::zero(),
+        unbonding: vec![],
+        stake_boost: Decimal256::one(),
+        partner_boost: Decimal256::zero(),
+        tier_principal: [Decimal256::zero(); 4],
+        tier_index: [Decimal256::zero(); 4],
+    }
+}
 
-    // swap ust for aust
-    anchor::deposit_stable_msg(deps, state.anc_market, "uust", amount)?;
-    Ok(Response::new().add_attribute("method", "make_deposit_and_convert_to_aust"))
+fn make_deposit_and_convert_to_aust(deps: Deps, env: &Env, info: &MessageInfo, campaign: &mut Campaign, amount: Uint128) -> Result<Vec<CosmosMsg>, ContractError> {
+    // funds are sent from the user to the protocol along with the message
+    if amount.is_zero() {
+        return Err(ContractError::InvalidZeroAmount {});
+    }
+    if sent_amount(info, "uust") != amount {
+        return Err(ContractError::DepositFundsMismatch {});
+    }
+    // swap ust for aust, counting what anchor mints at the current exchange rate towards the campaign
+    let epoch_state = anchor::epoch_state(deps, campaign.anc_market.clone(), env.block.height)?;
+    let net_amount = anchor::deduct_tax(deps, coin(amount.u128(), "uust"))?.amount;
+    campaign.aust_balance = campaign.aust_balance.checked_add(to_uint128(Uint256::from(net_amount) / epoch_state.exchange_rate)?)?;
+    Ok(anchor::deposit_stable_msg(deps, campaign.anc_market.clone(), "uust", amount)?)
 }
 
-fn some_withdraw_helper(mut entry: Entry, time: u64, mut amount: Uint128) -> Result<Entry, ContractError> {
+pub fn some_withdraw_helper(mut entry: Entry, time: u64, mut amount: Uint128, order: &DepletionOrder) -> Result<Entry, ContractError> {
     if entry.ust_deposited == Uint128::zero() {
         return Err(ContractError::CannotWithdrawBalanceZero {});
     }
@@ -263,41 +1529,96 @@ fn some_withdraw_helper(mut entry: Entry, time: u64, mut amount: Uint128) -> Res
         return Err(ContractError::CannotWithdrawGreaterThanBalance {});
     }
     let withdraw = Withdraw {
-        amount: amount,
-        time: time,
+        amount,
+        time,
     };
     entry.ust_withdraw_log.push(withdraw);
 
-    //let mut dynamic_reward_log_clone = entry.dynamic_reward_log;
-    for reward in &mut entry.dynamic_reward_log {
-        let reward: &mut Reward = reward;
-        if reward.amount > Uint128::zero() {
-            if reward.amount > amount {
-                reward.amount -= amount;
-                break
-            } else if reward.amount == amount {
-                reward.amount = Uint128::zero();
-                break
-            } else if reward.amount < amount {
-                amount -= reward.amount;
-                reward.amount = Uint128::zero();
-            }
+    for tranche_id in depletion_sequence(&entry.dynamic_reward_log, order) {
+        let reward = &mut entry.dynamic_reward_log[tranche_id];
+        if reward.amount > amount {
+            reward.amount -= amount;
+            break
         }
+        amount -= reward.amount;
+        reward.amount = Uint128::zero();
+        if amount.is_zero() {
+            break
+        }
+    }
+    Ok(entry)
+}
+
+// the order tranches are drawn down in, lowest tier first takes the newest of a tier first
+fn depletion_sequence(dynamic_reward_log: &[Reward], order: &DepletionOrder) -> Vec<usize> {
+    let mut sequence: Vec<usize> = (0..dynamic_reward_log.len())
+        .filter(|tranche_id| !dynamic_reward_log[*tranche_id].amount.is_zero())
+        .collect();
+    match order {
+        DepletionOrder::OldestFirst => {},
+        DepletionOrder::NewestFirst => sequence.reverse(),
+        DepletionOrder::LowestTierFirst => sequence.sort_by_key(|tranche_id| {
+            let tranche = &dynamic_reward_log[*tranche_id];
+            (tranche.reward_tier, Reverse(tranche.time), Reverse(*tranche_id))
+        }),
+    }
+    sequence
+}
+
+// the part of each tranche a withdrawal removes, keeping the time and tier it had reached
+pub fn depleted_tranches(before: &[Reward], after: &[Reward]) -> Vec<Reward> {
+    before
+        .iter()
+        .zip(after.iter())
+        .filter(|(old, new)| old.amount > new.amount)
+        .map(|(old, new)| Reward {
+            amount: old.amount - new.amount,
+            time: old.time,
+            reward_tier: old.reward_tier,
+        })
+        .collect()
+}
+
+// merges tranches moved in from another entry, keeping the log in deposit order
+pub fn some_transfer_helper(mut entry: Entry, tranches: &[Reward], amount: Uint128, time: u64) -> StdResult<Entry> {
+    entry.ust_deposited = entry.ust_deposited.checked_add(amount)?;
+    entry.ust_deposit_log.push(Deposit {
+        amount,
+        time,
+    });
+    for tranche in tranches {
+        let position = entry.dynamic_reward_log.partition_point(|existing| existing.time <= tranche.time);
+        entry.dynamic_reward_log.insert(position, tranche.clone());
     }
-    //entry.dynamic_reward_log = dynamic_reward_log_clone;
     Ok(entry)
 }
 
-fn convert_from_aust_and_make_withdraw(deps: Deps, info: MessageInfo, amount: Uint128) -> Result<Response, ContractError> {
-    let state = STATE.load(deps.storage)?;
-    // swap from aust to ust
-    anchor::redeem_stable_msg(deps, state.anc_market, state.aust_contract, amount)?;
-    // transfer funds from contract to users wallet
-    let mut response: Response = Default::default();
-    let coin_amount = coins(amount.u128(), "uust");
-    response.messages = vec![SubMsg::new(BankMsg::Send {
-        to_address: info.sender.to_string(),
-        amount: coin_amount,
-    })];
-    Ok(Response::new().add_attribute("method", "convert_from_aust_and_make_withdraw"))
-}
\ No newline at end of file
+fn convert_from_aust_and_make_withdraw(deps: Deps, env: &Env, campaign: &mut Campaign, to_address: &Addr, amount: Uint128) -> Result<Vec<CosmosMsg>, ContractError> {
+    // swap from aust to ust at the current exchange rate
+    let epoch_state = anchor::epoch_state(deps, campaign.anc_market.clone(), env.block.height)?;
+    let aust_amount = Uint256::from(amount) / epoch_state.exchange_rate;
+    campaign.aust_balance = campaign.aust_balance.saturating_sub(to_uint128(aust_amount)?);
+    let mut messages = anchor::redeem_stable_msg(deps, campaign.anc_market.clone(), campaign.aust_contract.clone(), aust_amount.into())?;
+    // transfer funds from contract to users wallet, taxed leaving anchor and again on the way out
+    let redeemed = anchor::deduct_tax(deps, coin(amount.u128(), "uust"))?;
+    messages.push(CosmosMsg::Bank(BankMsg::Send {
+        to_address: to_address.to_string(),
+        amount: vec![anchor::deduct_tax(deps, redeemed)?],
+    }));
+    Ok(messages)
+}
+
+fn refund_from_aust(deps: Deps, env: &Env, campaign: &mut Campaign, to_address: &Addr, amount: Uint128) -> Result<(Vec<CosmosMsg>, Uint128), ContractError> {
+    // redeem the entry's share of the campaign's aust, the yield it has earned included
+    let epoch_state = anchor::epoch_state(deps, campaign.anc_market.clone(), env.block.height)?;
+    let aust_amount = refund_aust(campaign, amount);
+    let ust_amount = to_uint128(aust_amount * epoch_state.exchange_rate)?;
+    campaign.aust_balance = campaign.aust_balance.saturating_sub(to_uint128(aust_amount)?);
+    let mut messages = anchor::redeem_stable_msg(deps, campaign.anc_market.clone(), campaign.aust_contract.clone(), aust_amount.into())?;
+    let redeemed = anchor::deduct_tax(deps, coin(ust_amount.u128(), "uust"))?;
+    messages.push(CosmosMsg::Bank(BankMsg::Send {
+        to_address: to_address.to_string(),
+        amount: vec![anchor::deduct_tax(deps, redeemed)?],
+    }));
+    Ok((messages, ust_amount))
+}
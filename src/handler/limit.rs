@@ -0,0 +1,96 @@
+use std::convert::TryInto;
+
+use cosmwasm_std::{Addr, StdError, StdResult, Storage, Uint128};
+use cw_storage_plus::U64Key;
+
+use crate::state::{Campaign, QueuedTotal, SellEpoch, ADDRESS_SELL_EPOCHS, QUEUED_SELLS, SELL_EPOCHS};
+
+// sells an address can have waiting at once, so nobody can fill the queue with blocked entries
+pub const MAX_QUEUED_SELLS: u32 = 5;
+
+pub struct SellAllowance {
+    pub epoch: u64,
+    pub global_remaining: Option<Uint128>,
+    pub address_remaining: Option<Uint128>,
+}
+
+impl SellAllowance {
+    // MIN that can still be sold this epoch, None when neither cap is set
+    pub fn available(&self) -> Option<Uint128> {
+        match (self.global_remaining, self.address_remaining) {
+            (Some(global), Some(address)) => Some(global.min(address)),
+            (global, address) => global.or(address),
+        }
+    }
+}
+
+pub fn epoch(campaign: &Campaign, time: u64) -> u64 {
+    time / campaign.sell_epoch_length
+}
+
+fn sold(record: Option<SellEpoch>, epoch: u64) -> Uint128 {
+    match record {
+        Some(record) if record.epoch == epoch => record.sold,
+        _ => Uint128::zero(),
+    }
+}
+
+pub fn allowance(storage: &dyn Storage, campaign: &Campaign, address: &Addr, time: u64) -> StdResult<SellAllowance> {
+    let epoch = epoch(campaign, time);
+    let global_sold = sold(SELL_EPOCHS.may_load(storage, U64Key::new(campaign.id))?, epoch);
+    let address_sold = sold(ADDRESS_SELL_EPOCHS.may_load(storage, (U64Key::new(campaign.id), address))?, epoch);
+    Ok(SellAllowance {
+        epoch,
+        global_remaining: campaign.sell_global_cap.map(|cap| cap.saturating_sub(global_sold)),
+        address_remaining: campaign.sell_address_cap.map(|cap| cap.saturating_sub(address_sold)),
+    })
+}
+
+// counts a sale against both caps for the current epoch
+pub fn record_sale(storage: &mut dyn Storage, campaign: &Campaign, address: &Addr, time: u64, amount: Uint128) -> StdResult<()> {
+    let epoch = epoch(campaign, time);
+    let global = SellEpoch {
+        epoch,
+        sold: sold(SELL_EPOCHS.may_load(storage, U64Key::new(campaign.id))?, epoch).checked_add(amount)?,
+    };
+    SELL_EPOCHS.save(storage, U64Key::new(campaign.id), &global)?;
+    let address_epoch = SellEpoch {
+        epoch,
+        sold: sold(ADDRESS_SELL_EPOCHS.may_load(storage, (U64Key::new(campaign.id), address))?, epoch).checked_add(amount)?,
+    };
+    ADDRESS_SELL_EPOCHS.save(storage, (U64Key::new(campaign.id), address), &address_epoch)
+}
+
+pub fn queued(storage: &dyn Storage, campaign: &Campaign, address: &Addr) -> StdResult<QueuedTotal> {
+    Ok(QUEUED_SELLS.may_load(storage, (U64Key::new(campaign.id), address))?.unwrap_or_default())
+}
+
+pub fn add_queued(storage: &mut dyn Storage, campaign: &Campaign, address: &Addr, amount: Uint128) -> StdResult<()> {
+    let mut total = queued(storage, campaign, address)?;
+    total.amount = total.amount.checked_add(amount)?;
+    total.sells += 1;
+    QUEUED_SELLS.save(storage, (U64Key::new(campaign.id), address), &total)
+}
+
+// takes filled or cancelled MIN off the address's queued total, counting the sell out once it has left
+// the queue and dropping the total once nothing is left
+pub fn remove_queued(storage: &mut dyn Storage, campaign: &Campaign, address: &Addr, amount: Uint128, closed: bool) -> StdResult<()> {
+    let mut total = queued(storage, campaign, address)?;
+    total.amount = total.amount.checked_sub(amount)?;
+    if closed {
+        total.sells = total.sells.saturating_sub(1);
+    }
+    if total.sells == 0 {
+        QUEUED_SELLS.remove(storage, (U64Key::new(campaign.id), address));
+        Ok(())
+    } else {
+        QUEUED_SELLS.save(storage, (U64Key::new(campaign.id), address), &total)
+    }
+}
+
+// sell queue keys are big-endian ids so the queue ranges oldest first
+pub fn queue_id(key: &[u8]) -> StdResult<u64> {
+    key.try_into()
+        .map(u64::from_be_bytes)
+        .map_err(|_| StdError::generic_err("Invalid sell queue key"))
+}
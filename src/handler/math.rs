@@ -0,0 +1,18 @@
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{OverflowError, OverflowOperation, StdResult, Uint128};
+
+// narrows a full-precision result back to a token amount, failing instead of truncating
+pub fn to_uint128(value: Uint256) -> StdResult<Uint128> {
+    if value > Uint256::from(u128::MAX) {
+        return Err(OverflowError::new(OverflowOperation::Mul, value, u128::MAX).into());
+    }
+    Ok(value.into())
+}
+
+pub fn decimal(value: impl Into<Uint256>) -> Decimal256 {
+    Decimal256::from_uint256(value)
+}
+
+pub fn ratio(numerator: impl Into<Uint256>, denominator: impl Into<Uint256>) -> Decimal256 {
+    Decimal256::from_ratio(numerator.into().0, denominator.into().0)
+}
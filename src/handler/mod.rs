@@ -1,3 +1,14 @@
+pub mod core;
 pub mod execute;
 pub mod query;
-pub mod anchor;
\ No newline at end of file
+pub mod anchor;
+pub mod simulate;
+pub mod reward;
+pub mod reply;
+pub mod price;
+pub mod terraswap;
+pub mod math;
+pub mod limit;
+pub mod referral;
+pub mod partner;
+pub mod split;
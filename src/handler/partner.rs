@@ -0,0 +1,112 @@
+use cosmwasm_bignumber::Decimal256;
+use cosmwasm_std::{Addr, Deps, Order, StdError, StdResult, Uint128};
+use cw20::{BalanceResponse, Cw20QueryMsg};
+use cw_storage_plus::U64Key;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::ContractError;
+use crate::state::{BoostBracket, Entry, PartnerBoost, PartnerKind, PARTNER_BOOSTS};
+
+const TOKENS_PAGE: u32 = 30;
+
+// the part of the CW721 query interface needed to count an owner's tokens
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw721QueryMsg {
+    Tokens { owner: String, start_after: Option<String>, limit: Option<u32> },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokensResponse {
+    pub tokens: Vec<String>,
+}
+
+// brackets climb strictly in holding and never lower the boost
+pub fn validate_brackets(brackets: &[BoostBracket]) -> Result<(), ContractError> {
+    if brackets.is_empty() || brackets.windows(2).any(|pair| pair[1].min_holding <= pair[0].min_holding || pair[1].boost < pair[0].boost) {
+        return Err(ContractError::InvalidBoostBrackets {});
+    }
+    Ok(())
+}
+
+fn holding(deps: Deps, contract: &Addr, partner: &PartnerBoost, address: &Addr) -> StdResult<Uint128> {
+    match partner.kind {
+        PartnerKind::Cw20 => {
+            let balance: BalanceResponse = deps.querier.query_wasm_smart(contract, &Cw20QueryMsg::Balance {
+                address: address.to_string(),
+            })?;
+            Ok(balance.balance)
+        },
+        PartnerKind::Cw721 => {
+            // only as many tokens as the top bracket asks for need counting, a page at a time since
+            // cw721-base never returns more than 30 tokens a query
+            let needed = partner.brackets.last().map_or(0, |bracket| bracket.min_holding.u128());
+            let mut held = 0u128;
+            let mut start_after = None;
+            while held < needed {
+                let page: TokensResponse = deps.querier.query_wasm_smart(contract, &Cw721QueryMsg::Tokens {
+                    owner: address.to_string(),
+                    start_after,
+                    limit: Some(TOKENS_PAGE),
+                })?;
+                held += page.tokens.len() as u128;
+                if page.tokens.len() < TOKENS_PAGE as usize {
+                    break;
+                }
+                start_after = page.tokens.last().cloned();
+            }
+            Ok(Uint128::from(held))
+        },
+    }
+}
+
+// the boost each of a campaign's partners currently gives an address. a partner that can't be queried gives
+// no boost instead of failing the call, so a broken partner never blocks the campaign
+pub fn active_boosts(deps: Deps, campaign_id: u64, address: &Addr) -> StdResult<Vec<(Addr, Decimal256)>> {
+    let partners: StdResult<Vec<_>> = PARTNER_BOOSTS.prefix(U64Key::new(campaign_id)).range(deps.storage, None, None, Order::Ascending).collect();
+    let mut boosts = vec![];
+    for (key, partner) in partners? {
+        let contract = Addr::unchecked(String::from_utf8(key).map_err(StdError::from)?);
+        let boost = match holding(deps, &contract, &partner, address) {
+            Ok(held) => partner.brackets
+                .iter()
+                .rev()
+                .find(|bracket| bracket.min_holding <= held)
+                .map(|bracket| bracket.boost)
+                .unwrap_or_else(Decimal256::zero),
+            Err(_) => Decimal256::zero(),
+        };
+        boosts.push((contract, boost));
+    }
+    Ok(boosts)
+}
+
+pub fn total_boost(boosts: &[(Addr, Decimal256)]) -> Decimal256 {
+    let mut total = Decimal256::zero();
+    for (_, boost) in boosts {
+        total += *boost;
+    }
+    total
+}
+
+// the boost the address's partner holdings give now
+pub fn current_boost(deps: Deps, campaign_id: u64, address: &Addr) -> StdResult<Decimal256> {
+    Ok(total_boost(&active_boosts(deps, campaign_id, address)?))
+}
+
+// queries the address's partner holdings so the entry accrues at the boost they give now
+pub fn refresh_boost(deps: Deps, campaign_id: u64, entry: &mut Entry, address: &Addr) -> StdResult<()> {
+    entry.partner_boost = current_boost(deps, campaign_id, address)?;
+    Ok(())
+}
+
+// the boost the time since the entry's last settle accrues at, the lower of the boost stored then and the
+// boost held now, so partner tokens only count for time they were held throughout
+pub fn settled_boost(entry: &Entry, current: Decimal256) -> Decimal256 {
+    if current < entry.partner_boost {
+        current
+    } else {
+        entry.partner_boost
+    }
+}
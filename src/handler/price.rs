@@ -0,0 +1,116 @@
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{Deps, DepsMut, Env, StdResult, Uint128};
+use cw_storage_plus::U64Key;
+
+use crate::ContractError;
+use crate::handler::math::{decimal, to_uint128};
+use crate::handler::terraswap;
+use crate::state::{Campaign, PriceObservation, PriceSource, PRICE_OBSERVATIONS};
+
+const MAX_OBSERVATIONS: usize = 30;
+
+// UST paid for `amount` MIN at a price in UST per MIN
+pub fn sellback_amount(price: Decimal256, amount: Uint128) -> StdResult<Uint128> {
+    to_uint128(Uint256::from(amount) * price)
+}
+
+pub fn validate_sellback_price(price: Decimal256) -> Result<(), ContractError> {
+    if price.is_zero() {
+        return Err(ContractError::InvalidSellbackPrice {});
+    }
+    Ok(())
+}
+
+pub fn validate_sellback_source(source: &PriceSource, floor: Decimal256, ceiling: Option<Decimal256>) -> Result<(), ContractError> {
+    if matches!(ceiling, Some(ceiling) if ceiling < floor) {
+        return Err(ContractError::InvalidSellbackBand {});
+    }
+    // a pair's price can be pushed around, so it is never paid out above a set ceiling
+    if !matches!(source, PriceSource::Static {}) && ceiling.is_none() {
+        return Err(ContractError::SellbackCeilingRequired {});
+    }
+    if let PriceSource::Twap { window, max_staleness, .. } = source {
+        if *window == 0 || *max_staleness == 0 {
+            return Err(ContractError::InvalidTwapWindow {});
+        }
+    }
+    Ok(())
+}
+
+// the price the configured source reports, before the floor and ceiling are applied
+pub fn source_price(deps: Deps, env: &Env, campaign: &Campaign) -> Result<Decimal256, ContractError> {
+    match &campaign.sellback_source {
+        PriceSource::Static {} => Ok(campaign.sellback_price),
+        PriceSource::Pair { pair } => Ok(pair_price(deps, campaign, pair.to_string())?),
+        PriceSource::Twap { window, max_staleness, .. } => {
+            let now = env.block.time.seconds();
+            let observations = PRICE_OBSERVATIONS.may_load(deps.storage, U64Key::new(campaign.id))?.unwrap_or_default();
+            let latest = observations.last().ok_or(ContractError::InsufficientPriceObservations {})?;
+            if now - latest.time > *max_staleness {
+                return Err(ContractError::StaleSellbackPrice { age: now - latest.time });
+            }
+            twap(&observations, now, *window).ok_or(ContractError::InsufficientPriceObservations {})
+        },
+    }
+}
+
+pub fn effective_price(deps: Deps, env: &Env, campaign: &Campaign) -> Result<Decimal256, ContractError> {
+    let price = source_price(deps, env, campaign)?.max(campaign.sellback_floor);
+    Ok(match campaign.sellback_ceiling {
+        Some(ceiling) => price.min(ceiling),
+        None => price,
+    })
+}
+
+// samples the pair's spot price into the observations the twap is averaged over, at most
+// MAX_OBSERVATIONS to a window, keeping the last observation from before the window so the whole
+// window stays covered
+pub fn record_observation(deps: DepsMut, env: &Env, campaign: &Campaign) -> Result<PriceObservation, ContractError> {
+    let (pair, window) = match &campaign.sellback_source {
+        PriceSource::Twap { pair, window, .. } => (pair.to_string(), *window),
+        _ => return Err(ContractError::SellbackSourceNotTwap {}),
+    };
+    let now = env.block.time.seconds();
+    let mut observations = PRICE_OBSERVATIONS.may_load(deps.storage, U64Key::new(campaign.id))?.unwrap_or_default();
+    if let Some(latest) = observations.last() {
+        let next = latest.time.saturating_add((window / MAX_OBSERVATIONS as u64).max(1));
+        if now < next {
+            return Err(ContractError::ObservationTooSoon { time: next });
+        }
+    }
+    let observation = PriceObservation {
+        time: now,
+        price: pair_price(deps.as_ref(), campaign, pair)?,
+    };
+    observations.push(observation.clone());
+    let start = now.saturating_sub(window);
+    while observations.len() > 1 && (observations[1].time <= start || observations.len() > MAX_OBSERVATIONS + 1) {
+        observations.remove(0);
+    }
+    PRICE_OBSERVATIONS.save(deps.storage, U64Key::new(campaign.id), &observations)?;
+    Ok(observation)
+}
+
+fn pair_price(deps: Deps, campaign: &Campaign, pair: String) -> StdResult<Decimal256> {
+    terraswap::spot_price(deps, pair, &campaign.reward_token, "uust")
+}
+
+// each observation holds until the next one, the latest until `now`
+fn twap(observations: &[PriceObservation], now: u64, window: u64) -> Option<Decimal256> {
+    let start = now.saturating_sub(window);
+    let mut weighted = Decimal256::zero();
+    let mut duration: u64 = 0;
+    for (index, observation) in observations.iter().enumerate() {
+        let from = observation.time.max(start);
+        let to = observations.get(index + 1).map_or(now, |next| next.time);
+        if to > from {
+            weighted += observation.price * decimal(to - from);
+            duration += to - from;
+        }
+    }
+    if duration == 0 {
+        None
+    } else {
+        Some(weighted / decimal(duration))
+    }
+}
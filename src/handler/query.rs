@@ -1,37 +1,358 @@
-use cosmwasm_std::{Deps, StdResult};
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use crate::ContractError;
+use cosmwasm_std::{Addr, Deps, Env, Order, StdError, StdResult, Uint128};
 
-use crate::{msg::{EntryResponse, StateResponse }, state::{STATE, ENTRIES}};
+use crate::{msg::{RewardBudgetResponse, SellbackPriceResponse, SellbackReserveResponse, ActivePartnerBoost, PartnerBoostsResponse, PartnerBoostResponse, ReferralResponse, ReferrerResponse, SellAllowanceResponse, SellQueueResponse, QueuedSellResponse, DepositedResponse, DepositorRank, DepositorRanking, EntryResponse, TopDepositorsResponse, ProjectedRewardsResponse, CampaignResponse, CampaignsResponse, ConfigResponse, StatsResponse, TrancheProjection}, state::{campaign_key, entries, entry_address, Campaign, CampaignStatus, Reward, CAMPAIGNS, CONFIG, DEPOSITED, TOTAL_DEPOSITED, SELL_QUEUE, REFERRALS, REFERRERS, PARTNER_BOOSTS}};
+use cw_storage_plus::{Bound, U64Key};
+use crate::handler::{anchor, limit, partner, price, referral, reward, split};
+use crate::handler::math::{ratio, to_uint128};
 
-pub fn query_entry(deps: Deps, entry_address: String) -> StdResult<EntryResponse> {
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+pub fn query_entry(deps: Deps, campaign_id: u64, entry_address: String) -> StdResult<EntryResponse> {
     let valid_address = deps.api.addr_validate(&entry_address)?;
-    let entry = ENTRIES.load(deps.storage, &valid_address)?;
+    let campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let mut entry = entries().load(deps.storage, campaign_key(campaign_id, &valid_address))?;
+    let partner_boosts = partner::active_boosts(deps, campaign_id, &valid_address)?;
+    entry.partner_boost = partner::total_boost(&partner_boosts);
+    entry.stake_boost = reward::stake_boost(&campaign, &entry);
     Ok(EntryResponse { 
+        stake_boost: entry.stake_boost,
+        reward_boost: reward::reward_boost(&entry),
+        partner_boosts: partner_boosts
+            .into_iter()
+            .map(|(contract, boost)| ActivePartnerBoost { contract, boost })
+            .collect(),
         claimable_reward: entry.claimable_reward,
         ust_deposited: entry.ust_deposited,
         averaged_reward_rate: entry.averaged_reward_rate,
+        lifetime_reward_rate: reward::lifetime_reward_rate(&entry),
+        reward_accrued: entry.reward_accrued,
         ust_deposit_log: entry.ust_deposit_log,
         ust_withdraw_log: entry.ust_withdraw_log,
         dynamic_reward_log: entry.dynamic_reward_log,
+        auto_compound: entry.auto_compound,
+        staked_reward: entry.staked_reward,
+        unbonding: entry.unbonding,
+    })
+}
+
+pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    Ok(ConfigResponse {
+        owner: config.owner,
+        campaign_count: config.campaign_count,
+    })
+}
+
+pub fn query_campaign(deps: Deps, campaign_id: u64) -> StdResult<CampaignResponse> {
+    let campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    Ok(campaign_response(campaign))
+}
+
+pub fn query_campaigns(deps: Deps, start_after: Option<u64>, limit: Option<u32>) -> StdResult<CampaignsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|id| Bound::exclusive(U64Key::new(id)));
+    let campaigns: StdResult<Vec<CampaignResponse>> = CAMPAIGNS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| Ok(campaign_response(item?.1)))
+        .collect();
+    Ok(CampaignsResponse { campaigns: campaigns? })
+}
+
+fn campaign_response(campaign: Campaign) -> CampaignResponse {
+    CampaignResponse {
+        id: campaign.id,
+        owner: campaign.owner,
+        treasury_wallet: campaign.treasury_wallet,
+        yield_split: campaign.yield_split,
+        reward_contract: campaign.reward_contract,
+        reward_token: campaign.reward_token,
+        reward_mode: campaign.reward_mode,
+        dp_token: campaign.dp_token,
+        depletion_order: campaign.depletion_order,
+        ust_deposited: campaign.ust_deposited,
+        start_time: campaign.start_time,
+        end_time: campaign.end_time,
+        deposit_cap: campaign.deposit_cap,
+        soft_cap: campaign.soft_cap,
+        deadline: campaign.deadline,
+        status: campaign.status,
+        sellback_price: campaign.sellback_price,
+        anc_market: campaign.anc_market,
+        aust_contract: campaign.aust_contract,
+        tier0rate: campaign.tier0rate,
+        tier0time: campaign.tier0time,
+        tier1rate: campaign.tier1rate,
+        tier1time: campaign.tier1time,
+        tier2rate: campaign.tier2rate,
+        tier2time: campaign.tier2time,
+        tier3rate: campaign.tier3rate,
+        tier3time: campaign.tier3time,
+    }
+}
+
+pub fn query_projected_rewards(deps: Deps, env: Env, campaign_id: u64, entry_address: String, time: u64) -> StdResult<ProjectedRewardsResponse> {
+    let campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let valid_address = deps.api.addr_validate(&entry_address)?;
+    let mut entry = entries().load(deps.storage, campaign_key(campaign_id, &valid_address))?;
+    let now = env.block.time.seconds().max(entry.last_reward_time);
+    if time < now {
+        return Err(StdError::generic_err("Projection time must not be in the past"));
+    }
+    // nothing accrues once a campaign has failed
+    if campaign.status == CampaignStatus::Failed {
+        return Ok(ProjectedRewardsResponse {
+            time,
+            claimable_reward: Uint128::zero(),
+            projected_reward: Uint128::zero(),
+            averaged_reward_rate: Decimal256::zero(),
+            tranches: vec![],
+        });
+    }
+    // up to now the entry accrues at its stored boosts, the partner one no higher than what its holdings give
+    // now, from now on at the boosts its stake and holdings give, all of it throttled by the share of the
+    // budget left for what the campaign will owe by then
+    let current = partner::current_boost(deps, campaign_id, &valid_address)?;
+    entry.partner_boost = partner::settled_boost(&entry, current);
+    let referral_bonus = referral::pending_bonus(deps.storage, &campaign, &entry, &valid_address, entry.last_reward_time, time)?;
+    let ratio = reward::throttle_ratio(&campaign, &entry, referral_bonus, time)?;
+    let stored_boost = reward::reward_boost(&entry);
+    entry.partner_boost = current;
+    entry.stake_boost = reward::stake_boost(&campaign, &entry);
+    let boost = reward::reward_boost(&entry);
+    let project = |tranche: &Reward| -> StdResult<Uint128> {
+        let accrued = Uint256::from(reward::accrued_reward(&campaign, tranche, entry.last_reward_time, now)?) * stored_boost;
+        let ahead = Uint256::from(reward::accrued_reward(&campaign, tranche, now, time)?) * boost;
+        to_uint128((accrued + ahead) * ratio)
+    };
+
+    let mut projected_reward = Uint128::zero();
+    let mut tranches = vec![];
+    for tranche in entry.dynamic_reward_log.iter().filter(|tranche| !tranche.amount.is_zero()) {
+        let tranche_reward = project(tranche)?;
+        projected_reward = projected_reward.checked_add(tranche_reward)?;
+        tranches.push(TrancheProjection {
+            amount: tranche.amount,
+            time: tranche.time,
+            reward_tier: reward::reward_tier(&campaign, tranche, time),
+            projected_reward: tranche_reward,
+            promotions: reward::tier_promotions(&campaign, tranche, now),
+        });
+    }
+    Ok(ProjectedRewardsResponse {
+        time,
+        claimable_reward: entry.claimable_reward,
+        projected_reward,
+        averaged_reward_rate: reward::averaged_reward_rate(&campaign, &entry.dynamic_reward_log, time),
+        tranches,
+    })
+}
+
+pub fn query_stats(deps: Deps, env: Env, campaign_id: u64) -> StdResult<StatsResponse> {
+    let campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let epoch_state = anchor::epoch_state(deps, campaign.anc_market.clone(), env.block.height)?;
+    // the contract holds the aust of every campaign on the same aust token, each campaign is counted for
+    // the part of it its running estimate makes up
+    let held = anchor::aust_balance(deps, campaign.aust_contract.clone(), env.contract.address.to_string())?;
+    let mut estimated = Uint128::zero();
+    for item in CAMPAIGNS.range(deps.storage, None, None, Order::Ascending) {
+        let (_, other) = item?;
+        if other.aust_contract == campaign.aust_contract {
+            estimated = estimated.checked_add(other.aust_balance)?;
+        }
+    }
+    let aust_balance = if estimated.is_zero() { Uint128::zero() } else { held.multiply_ratio(campaign.aust_balance, estimated) };
+    let total_value_locked = Uint256::from(aust_balance) * epoch_state.exchange_rate;
+    let ust_deposited = Uint256::from(campaign.ust_deposited);
+    Ok(StatsResponse {
+        aust_balance,
+        exchange_rate: epoch_state.exchange_rate,
+        total_value_locked,
+        ust_deposited: campaign.ust_deposited,
+        unrealized_yield: if total_value_locked > ust_deposited { total_value_locked - ust_deposited } else { Uint256::zero() },
+        yield_cashed_out: campaign.yield_cashed_out,
+        sellback_reserve: campaign.sellback_reserve,
+        total_reward_accrued: campaign.total_reward_accrued,
+        total_reward_claimed: campaign.total_reward_claimed,
+        total_reward_bought_back: campaign.total_reward_bought_back,
+        depositor_count: campaign.depositor_count,
+    })
+}
+
+// a height reads the value as it stood at the start of that block
+pub fn query_deposited(deps: Deps, campaign_id: u64, entry_address: String, height: Option<u64>) -> StdResult<DepositedResponse> {
+    let valid_address = deps.api.addr_validate(&entry_address)?;
+    let ust_deposited = match height {
+        Some(height) => DEPOSITED.may_load_at_height(deps.storage, campaign_key(campaign_id, &valid_address), height)?,
+        None => DEPOSITED.may_load(deps.storage, campaign_key(campaign_id, &valid_address))?,
+    };
+    Ok(DepositedResponse {
+        ust_deposited: ust_deposited.unwrap_or_default(),
+        height,
+    })
+}
+
+pub fn query_total_deposited(deps: Deps, campaign_id: u64, height: Option<u64>) -> StdResult<DepositedResponse> {
+    let ust_deposited = match height {
+        Some(height) => TOTAL_DEPOSITED.may_load_at_height(deps.storage, U64Key::new(campaign_id), height)?,
+        None => TOTAL_DEPOSITED.may_load(deps.storage, U64Key::new(campaign_id))?,
+    };
+    Ok(DepositedResponse {
+        ust_deposited: ust_deposited.unwrap_or_default(),
+        height,
     })
 }
 
-pub fn query_state(deps: Deps) -> StdResult<StateResponse> {
-    let state = STATE.load(deps.storage)?;
-    Ok(StateResponse { 
-        owner: state.owner,
-        treasury_wallet: state.treasury_wallet,
-        reward_contract: state.reward_contract,
-        ust_deposited: state.ust_deposited,
-        sellback_price: state.sellback_price,
-        anc_market: state.anc_market,
-        aust_contract: state.aust_contract,
-        tier0rate: state.tier0rate,
-        tier0time: state.tier0time,
-        tier1rate: state.tier1rate,
-        tier1time: state.tier1time,
-        tier2rate: state.tier2rate,
-        tier2time: state.tier2time,
-        tier3rate: state.tier3rate,
-        tier3time: state.tier3time,
-    })
-}
\ No newline at end of file
+pub fn query_top_depositors(deps: Deps, env: Env, campaign_id: u64, limit: Option<u32>, by: DepositorRanking) -> StdResult<TopDepositorsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let entries = entries();
+    let ranked = match by {
+        DepositorRanking::UstDeposited => entries.idx.ust_deposited.sub_prefix(U64Key::new(campaign_id)).range(deps.storage, None, None, Order::Descending),
+        DepositorRanking::TimeWeightedDeposit => entries.idx.time_weighted_deposit.sub_prefix(U64Key::new(campaign_id)).range(deps.storage, None, None, Order::Descending),
+    };
+    let depositors: StdResult<Vec<DepositorRank>> = ranked
+        .take_while(|item| match (item, &by) {
+            (Ok((_, entry)), DepositorRanking::UstDeposited) => !entry.ust_deposited.is_zero(),
+            (Ok((_, entry)), DepositorRanking::TimeWeightedDeposit) => !entry.time_weighted_deposit.is_zero(),
+            (Err(_), _) => true,
+        })
+        .take(limit)
+        .map(|item| {
+            let (key, entry) = item?;
+            Ok(DepositorRank {
+                address: entry_address(&key)?,
+                ust_deposited: entry.ust_deposited,
+                time_weighted_deposit: entry.time_weighted_deposit,
+                settled_time: entry.last_reward_time,
+                current_time_weighted_deposit: reward::time_weighted_deposit(&entry, env.block.time.seconds())?,
+            })
+        })
+        .collect();
+    Ok(TopDepositorsResponse { depositors: depositors? })
+}
+
+pub fn query_reward_budget(deps: Deps, env: Env, campaign_id: u64) -> StdResult<RewardBudgetResponse> {
+    let campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let time = env.block.time.seconds();
+    // running totals kept as entries settle, so this stays cheap however many entries there are
+    let pending_reward = reward::outstanding_reward(&campaign, time)?;
+    let emission_rate = reward::reward_rate(&campaign);
+    let remaining_budget = reward::remaining_budget(&campaign).saturating_sub(pending_reward);
+    // at the current tiers, ignoring promotions and deposits still to come
+    let estimated_exhaustion_time = if emission_rate.is_zero() {
+        None
+    } else {
+        let seconds = to_uint128(Uint256::from(remaining_budget) / emission_rate)?;
+        Some(time.saturating_add(seconds.u128().min(u64::MAX as u128) as u64))
+    };
+    Ok(RewardBudgetResponse {
+        reward_budget: campaign.reward_budget,
+        total_reward_accrued: campaign.total_reward_accrued,
+        pending_reward,
+        remaining_budget,
+        emission_rate,
+        estimated_exhaustion_time,
+    })
+}
+
+pub fn query_sellback_price(deps: Deps, env: Env, campaign_id: u64) -> StdResult<SellbackPriceResponse> {
+    let campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let to_std = |err: ContractError| StdError::generic_err(err.to_string());
+    Ok(SellbackPriceResponse {
+        source_price: price::source_price(deps, &env, &campaign).map_err(to_std)?,
+        effective_price: price::effective_price(deps, &env, &campaign).map_err(to_std)?,
+        source: campaign.sellback_source,
+        floor: campaign.sellback_floor,
+        ceiling: campaign.sellback_ceiling,
+    })
+}
+
+// coverage is the reserve over what buying back every outstanding MIN at the current price would cost
+pub fn query_sellback_reserve(deps: Deps, env: Env, campaign_id: u64) -> StdResult<SellbackReserveResponse> {
+    let campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let sellback_price = price::effective_price(deps, &env, &campaign)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+    let outstanding_reward = campaign.total_reward_accrued.saturating_sub(campaign.total_reward_bought_back);
+    let liability = price::sellback_amount(sellback_price, outstanding_reward)?;
+    Ok(SellbackReserveResponse {
+        reserve: campaign.sellback_reserve,
+        reserve_share: split::reserve_share(&campaign.yield_split),
+        outstanding_reward,
+        sellback_price,
+        liability,
+        coverage: if liability.is_zero() { None } else { Some(ratio(campaign.sellback_reserve, liability)) },
+    })
+}
+
+pub fn query_sell_allowance(deps: Deps, env: Env, campaign_id: u64, entry_address: String) -> StdResult<SellAllowanceResponse> {
+    let campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let valid_address = deps.api.addr_validate(&entry_address)?;
+    let allowance = limit::allowance(deps.storage, &campaign, &valid_address, env.block.time.seconds())?;
+    let queued = limit::queued(deps.storage, &campaign, &valid_address)?;
+    Ok(SellAllowanceResponse {
+        epoch: allowance.epoch,
+        epoch_end: (allowance.epoch + 1).saturating_mul(campaign.sell_epoch_length),
+        global_remaining: allowance.global_remaining,
+        address_remaining: allowance.address_remaining,
+        available: allowance.available(),
+        queued: queued.amount,
+        queued_sells: queued.sells,
+    })
+}
+
+pub fn query_sell_queue(deps: Deps, campaign_id: u64, start_after: Option<u64>, limit: Option<u32>) -> StdResult<SellQueueResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|id| Bound::exclusive(U64Key::new(id)));
+    let sells: StdResult<Vec<QueuedSellResponse>> = SELL_QUEUE
+        .prefix(U64Key::new(campaign_id))
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (key, sell) = item?;
+            Ok(QueuedSellResponse {
+                id: limit::queue_id(&key)?,
+                entry_address: sell.entry_address,
+                amount: sell.amount,
+                time: sell.time,
+                reward_token: sell.reward_token,
+            })
+        })
+        .collect();
+    Ok(SellQueueResponse { sells: sells? })
+}
+
+pub fn query_referral(deps: Deps, campaign_id: u64, entry_address: String) -> StdResult<ReferralResponse> {
+    let valid_address = deps.api.addr_validate(&entry_address)?;
+    Ok(ReferralResponse {
+        referral: REFERRALS.may_load(deps.storage, campaign_key(campaign_id, &valid_address))?,
+    })
+}
+
+pub fn query_referrer(deps: Deps, campaign_id: u64, address: String) -> StdResult<ReferrerResponse> {
+    let valid_address = deps.api.addr_validate(&address)?;
+    let info = REFERRERS.may_load(deps.storage, campaign_key(campaign_id, &valid_address))?.unwrap_or_default();
+    Ok(ReferrerResponse {
+        referees: info.referees,
+        earned: info.earned,
+        unclaimed: info.unclaimed,
+    })
+}
+
+pub fn query_partner_boosts(deps: Deps, campaign_id: u64) -> StdResult<PartnerBoostsResponse> {
+    let partners: StdResult<Vec<PartnerBoostResponse>> = PARTNER_BOOSTS
+        .prefix(U64Key::new(campaign_id))
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (key, partner) = item?;
+            Ok(PartnerBoostResponse {
+                contract: Addr::unchecked(String::from_utf8(key).map_err(StdError::from)?),
+                kind: partner.kind,
+                brackets: partner.brackets,
+            })
+        })
+        .collect();
+    Ok(PartnerBoostsResponse { partners: partners? })
+}
@@ -0,0 +1,95 @@
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{Addr, Event, StdResult, Storage, Uint128};
+
+use crate::handler::math::to_uint128;
+use crate::handler::reward;
+use crate::state::{campaign_key, Campaign, Entry, Referral, REFERRALS, REFERRERS};
+
+// records who referred an entry on its first deposit, locking in the current terms
+pub fn record_referral(storage: &mut dyn Storage, campaign: &Campaign, referee: &Addr, referrer: Addr, time: u64) -> StdResult<Referral> {
+    let referral = Referral {
+        referrer,
+        share: campaign.referral_share,
+        start: time,
+        end: time.saturating_add(campaign.referral_period),
+        earned: Uint128::zero(),
+    };
+    REFERRALS.save(storage, campaign_key(campaign.id, referee), &referral)?;
+    let mut info = REFERRERS.may_load(storage, campaign_key(campaign.id, &referral.referrer))?.unwrap_or_default();
+    info.referees += 1;
+    REFERRERS.save(storage, campaign_key(campaign.id, &referral.referrer), &info)?;
+    Ok(referral)
+}
+
+// the part of [from, to) that falls inside the referral period
+fn referral_window(referral: &Referral, from: u64, to: u64) -> Option<(u64, u64)> {
+    let (from, to) = (from.max(referral.start), to.min(referral.end));
+    if from < to {
+        Some((from, to))
+    } else {
+        None
+    }
+}
+
+// the share of its referee's reward a referrer is owed at `time`, zero outside the referral period
+pub fn active_share(storage: &dyn Storage, campaign: &Campaign, referee: &Addr, time: u64) -> StdResult<Decimal256> {
+    Ok(match REFERRALS.may_load(storage, campaign_key(campaign.id, referee))? {
+        Some(referral) if referral.start <= time && time < referral.end => referral.share,
+        _ => Decimal256::zero(),
+    })
+}
+
+// bonus the referee's accrual over [from, to) owes its referrer, before any budget throttling
+pub fn pending_bonus(storage: &dyn Storage, campaign: &Campaign, entry: &Entry, referee: &Addr, from: u64, to: u64) -> StdResult<Uint128> {
+    let referral = match REFERRALS.may_load(storage, campaign_key(campaign.id, referee))? {
+        Some(referral) => referral,
+        None => return Ok(Uint128::zero()),
+    };
+    match referral_window(&referral, from, to) {
+        Some((from, to)) => to_uint128(Uint256::from(reward::entry_reward(campaign, entry, from, to)?) * referral.share),
+        None => Ok(Uint128::zero()),
+    }
+}
+
+// credits the referrer its share of what the referee accrued over [from, to) inside the referral period,
+// out of whatever budget the referee's own accrual left
+pub fn credit_bonus(storage: &mut dyn Storage, campaign: &mut Campaign, entry: &Entry, referee: &Addr, from: u64, to: u64, ratio: Decimal256) -> StdResult<Option<Event>> {
+    let mut referral = match REFERRALS.may_load(storage, campaign_key(campaign.id, referee))? {
+        Some(referral) => referral,
+        None => return Ok(None),
+    };
+    let (from, to) = match referral_window(&referral, from, to) {
+        Some(window) => window,
+        None => return Ok(None),
+    };
+    let accrued = reward::entry_reward(campaign, entry, from, to)?;
+    let bonus = to_uint128(Uint256::from(accrued) * ratio * referral.share)?
+        .min(reward::remaining_budget(campaign));
+    if bonus.is_zero() {
+        return Ok(None);
+    }
+    referral.earned = referral.earned.checked_add(bonus)?;
+    REFERRALS.save(storage, campaign_key(campaign.id, referee), &referral)?;
+    let mut info = REFERRERS.may_load(storage, campaign_key(campaign.id, &referral.referrer))?.unwrap_or_default();
+    info.earned = info.earned.checked_add(bonus)?;
+    info.unclaimed = info.unclaimed.checked_add(bonus)?;
+    REFERRERS.save(storage, campaign_key(campaign.id, &referral.referrer), &info)?;
+    campaign.total_reward_accrued = campaign.total_reward_accrued.checked_add(bonus)?;
+    Ok(Some(Event::new("ipr_referral_reward")
+        .add_attribute("campaign_id", campaign.id.to_string())
+        .add_attribute("referrer", referral.referrer.to_string())
+        .add_attribute("referee", referee.to_string())
+        .add_attribute("amount", bonus.to_string())))
+}
+
+// hands a referrer's unclaimed bonus over to its claimable reward
+pub fn take_unclaimed(storage: &mut dyn Storage, campaign_id: u64, referrer: &Addr) -> StdResult<Uint128> {
+    let mut info = match REFERRERS.may_load(storage, campaign_key(campaign_id, referrer))? {
+        Some(info) => info,
+        None => return Ok(Uint128::zero()),
+    };
+    let unclaimed = info.unclaimed;
+    info.unclaimed = Uint128::zero();
+    REFERRERS.save(storage, campaign_key(campaign_id, referrer), &info)?;
+    Ok(unclaimed)
+}
@@ -0,0 +1,22 @@
+use cosmwasm_std::{DepsMut, Reply, Response};
+use cw_storage_plus::U64Key;
+
+use crate::ContractError;
+use crate::handler::execute::claim_event;
+use crate::state::{campaign_key, entries, CAMPAIGNS, PENDING_CLAIM};
+
+pub fn reply_claim(deps: DepsMut, _msg: Reply) -> Result<Response, ContractError> {
+    // the reward contract has paid out, so the claim can come off the entry
+    let pending = PENDING_CLAIM.load(deps.storage)?;
+    let mut campaign = CAMPAIGNS.load(deps.storage, U64Key::new(pending.campaign_id))?;
+    let key = campaign_key(pending.campaign_id, &pending.entry_address);
+    let mut entry = entries().load(deps.storage, key.clone())?;
+    entry.claimable_reward = entry.claimable_reward.checked_sub(pending.amount)?;
+    campaign.total_reward_claimed = campaign.total_reward_claimed.checked_add(pending.amount)?;
+    entries().save(deps.storage, key, &entry)?;
+    CAMPAIGNS.save(deps.storage, U64Key::new(campaign.id), &campaign)?;
+    PENDING_CLAIM.remove(deps.storage);
+    Ok(Response::new()
+        .add_attribute("method", "reply_claim")
+        .add_event(claim_event(&campaign, &pending.entry_address, pending.amount)))
+}
@@ -0,0 +1,299 @@
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{StdResult, Uint128};
+
+use crate::ContractError;
+use crate::handler::math::{decimal, ratio, to_uint128};
+use crate::msg::TierPromotion;
+use crate::state::{Entry, Reward, Campaign};
+
+pub type TierSchedule = [(Decimal256, u64); 4];
+
+pub fn tier_schedule(campaign: &Campaign) -> TierSchedule {
+    [
+        (campaign.tier0rate, campaign.tier0time),
+        (campaign.tier1rate, campaign.tier1time),
+        (campaign.tier2rate, campaign.tier2time),
+        (campaign.tier3rate, campaign.tier3time),
+    ]
+}
+
+// tier 0 starts at deposit, each later tier starts strictly after the one below it and pays at least as much
+pub fn validate_tier_schedule(schedule: &TierSchedule) -> Result<(), ContractError> {
+    if schedule[0].1 != 0 || schedule.windows(2).any(|tiers| tiers[1].1 <= tiers[0].1) {
+        return Err(ContractError::InvalidTierTimes {});
+    }
+    if schedule.windows(2).any(|tiers| tiers[1].0 < tiers[0].0) {
+        return Err(ContractError::InvalidTierRates {});
+    }
+    Ok(())
+}
+
+// the time a tranche enters each tier, a tier is never entered before the one below it
+fn tier_starts(schedule: &TierSchedule, tranche: &Reward) -> [u64; 4] {
+    let mut starts = [tranche.time; 4];
+    for tier in 1..4 {
+        starts[tier] = starts[tier - 1].max(tranche.time.saturating_add(schedule[tier].1));
+    }
+    starts
+}
+
+pub fn reward_tier(campaign: &Campaign, tranche: &Reward, time: u64) -> u8 {
+    let starts = tier_starts(&tier_schedule(campaign), tranche);
+    (1..4).take_while(|tier| starts[*tier] <= time).count() as u8
+}
+
+pub fn tier_promotions(campaign: &Campaign, tranche: &Reward, after: u64) -> Vec<TierPromotion> {
+    let starts = tier_starts(&tier_schedule(campaign), tranche);
+    (1..4)
+        .filter(|tier| starts[*tier] > after)
+        .map(|tier| TierPromotion {
+            reward_tier: tier as u8,
+            time: starts[tier],
+        })
+        .collect()
+}
+
+// MIN a tranche earns over [from, to), at the schedule that was in force over each part of it
+pub fn accrued_reward(campaign: &Campaign, tranche: &Reward, from: u64, to: u64) -> StdResult<Uint128> {
+    let mut reward = Uint256::zero();
+    let mut start = from;
+    for checkpoint in &campaign.tier_history {
+        if start >= to {
+            break;
+        }
+        if checkpoint.until > start {
+            let end = checkpoint.until.min(to);
+            reward += schedule_reward(&checkpoint.schedule, tranche, start, end);
+            start = end;
+        }
+    }
+    if to > start {
+        reward += schedule_reward(&tier_schedule(campaign), tranche, start, to);
+    }
+    to_uint128(reward)
+}
+
+// MIN a tranche earns over [from, to) at one schedule, switching rate at each tier boundary
+fn schedule_reward(schedule: &TierSchedule, tranche: &Reward, from: u64, to: u64) -> Uint256 {
+    let starts = tier_starts(schedule, tranche);
+    let mut reward = Uint256::zero();
+    for tier in 0..4 {
+        let start = starts[tier].max(from);
+        let end = if tier < 3 { starts[tier + 1].min(to) } else { to };
+        if end > start {
+            reward += Uint256::from(tranche.amount) * Uint256::from(end - start) * schedule[tier].0;
+        }
+    }
+    reward
+}
+
+// principal-weighted rate across the live tranches at the tier each holds at `time`
+pub fn averaged_reward_rate(campaign: &Campaign, dynamic_reward_log: &[Reward], time: u64) -> Decimal256 {
+    let principal: Uint128 = dynamic_reward_log.iter().map(|tranche| tranche.amount).sum();
+    if principal.is_zero() {
+        return Decimal256::zero();
+    }
+    tranche_emission(campaign, dynamic_reward_log, time) / decimal(principal)
+}
+
+pub struct Promotion {
+    pub tranche_id: usize,
+    pub old_tier: u8,
+    pub new_tier: u8,
+}
+
+pub struct Settlement {
+    pub accrued: Uint128,
+    pub promotions: Vec<Promotion>,
+}
+
+// MIN an entry has earned since it was last settled, boosted by its stake and partner holdings,
+// before any budget throttling
+pub fn pending_reward(campaign: &Campaign, entry: &Entry, time: u64) -> StdResult<Uint128> {
+    to_uint128(Uint256::from(entry_reward(campaign, entry, entry.last_reward_time, time)?) * reward_boost(entry))
+}
+
+// the multiplier on an entry's reward, one when nothing boosts it
+pub fn reward_boost(entry: &Entry) -> Decimal256 {
+    entry.stake_boost + entry.partner_boost
+}
+
+// multiplier a staked MIN balance puts on the entry's reward, growing with the stake per UST deposited,
+// the entry accrues at the one stored when it was last saved
+pub fn stake_boost(campaign: &Campaign, entry: &Entry) -> Decimal256 {
+    if entry.staked_reward.is_zero() || entry.ust_deposited.is_zero() {
+        return Decimal256::one();
+    }
+    let boost = campaign.stake_boost_rate * ratio(entry.staked_reward, entry.ust_deposited);
+    Decimal256::one() + boost.min(campaign.stake_boost_cap)
+}
+
+// MIN an entry's tranches earn over [from, to)
+pub fn entry_reward(campaign: &Campaign, entry: &Entry, from: u64, to: u64) -> StdResult<Uint128> {
+    let mut reward = Uint128::zero();
+    for tranche in &entry.dynamic_reward_log {
+        reward = reward.checked_add(accrued_reward(campaign, tranche, from, to)?)?;
+    }
+    Ok(reward)
+}
+
+pub fn remaining_budget(campaign: &Campaign) -> Uint128 {
+    campaign.reward_budget.saturating_sub(campaign.total_reward_accrued)
+}
+
+// MIN per second the entries and their referrers earn together, at the tiers and boosts they last settled at
+pub fn reward_rate(campaign: &Campaign) -> Decimal256 {
+    let schedule = tier_schedule(campaign);
+    let mut rate = Decimal256::zero();
+    for (principal, (tier_rate, _)) in campaign.tier_principal.iter().zip(schedule.iter()) {
+        rate += *principal * *tier_rate;
+    }
+    rate
+}
+
+// MIN earned per unit of principal at each tier, from the campaign's start up to `time`
+pub fn tier_index(campaign: &Campaign, time: u64) -> [Decimal256; 4] {
+    let elapsed = decimal(time.saturating_sub(campaign.outstanding_time));
+    let schedule = tier_schedule(campaign);
+    let mut index = campaign.tier_index;
+    for (tier_index, (tier_rate, _)) in index.iter_mut().zip(schedule.iter()) {
+        *tier_index += *tier_rate * elapsed;
+    }
+    index
+}
+
+// MIN the campaign's entries have earned and not yet settled as of `time`, estimated at the tier each
+// last settled at, tiers only climb so an entry settling can find a little more than it was counted for
+pub fn outstanding_reward(campaign: &Campaign, time: u64) -> StdResult<Uint128> {
+    let elapsed = time.saturating_sub(campaign.outstanding_time);
+    let accrued = to_uint128(Uint256::from(elapsed) * reward_rate(campaign))?;
+    Ok(campaign.outstanding_reward.checked_add(accrued)?)
+}
+
+// brings the outstanding estimate and the tier indexes up to `time`, at the rates in force until then
+pub fn advance_outstanding(campaign: &mut Campaign, time: u64) -> StdResult<()> {
+    if time > campaign.outstanding_time {
+        campaign.outstanding_reward = outstanding_reward(campaign, time)?;
+        campaign.tier_index = tier_index(campaign, time);
+        campaign.outstanding_time = time;
+    }
+    Ok(())
+}
+
+// what the campaign's outstanding estimate counts for the entry since it last settled
+pub fn estimated_reward(campaign: &Campaign, entry: &Entry, time: u64) -> StdResult<Uint128> {
+    let index = tier_index(campaign, time);
+    let mut reward = Decimal256::zero();
+    for ((now, then), principal) in index.iter().zip(entry.tier_index.iter()).zip(entry.tier_principal.iter()) {
+        if now > then {
+            reward += *principal * (*now - *then);
+        }
+    }
+    to_uint128(Uint256::one() * reward)
+}
+
+// one ratio for every entry of the campaign: the remaining budget over everything its entries and their
+// referrers have earned and not settled, with this entry counted at what it and its referrer really
+// earned, so a budget running low is shared out pro-rata instead of going to whoever settles first
+pub fn throttle_ratio(campaign: &Campaign, entry: &Entry, referral_bonus: Uint128, time: u64) -> StdResult<Decimal256> {
+    let outstanding = outstanding_reward(campaign, time)?
+        .saturating_sub(estimated_reward(campaign, entry, time)?)
+        .checked_add(pending_reward(campaign, entry, time)?)?
+        .checked_add(referral_bonus)?;
+    Ok(budget_ratio(campaign, outstanding))
+}
+
+// replaces the entry's part of the campaign's tier principal with the boosted principal it holds at
+// each tier at `time`, the campaign has to be advanced to `time` first. a referral share in force
+// weighs in too, since the referrer's bonus comes out of the same budget; it stays counted past the
+// referral's end until the entry next settles
+pub fn track_rate(campaign: &mut Campaign, entry: &mut Entry, referral_share: Decimal256, time: u64) {
+    let weight = reward_boost(entry) + referral_share;
+    let mut principal = [Decimal256::zero(); 4];
+    for tranche in &entry.dynamic_reward_log {
+        principal[reward_tier(campaign, tranche, time) as usize] += decimal(tranche.amount) * weight;
+    }
+    for ((total, old), new) in campaign.tier_principal.iter_mut().zip(entry.tier_principal.iter()).zip(principal.iter()) {
+        let others = if *total > *old { *total - *old } else { Decimal256::zero() };
+        *total = others + *new;
+    }
+    entry.tier_principal = principal;
+    entry.tier_index = tier_index(campaign, time);
+}
+
+// share of the pending reward the remaining budget can pay, throttled pro-rata once it runs low
+pub fn budget_ratio(campaign: &Campaign, pending: Uint128) -> Decimal256 {
+    let remaining = remaining_budget(campaign);
+    if pending <= remaining {
+        Decimal256::one()
+    } else {
+        ratio(remaining, pending)
+    }
+}
+
+fn tranche_emission(campaign: &Campaign, dynamic_reward_log: &[Reward], time: u64) -> Decimal256 {
+    let schedule = tier_schedule(campaign);
+    let mut rate = Decimal256::zero();
+    for tranche in dynamic_reward_log {
+        rate += decimal(tranche.amount) * schedule[reward_tier(campaign, tranche, time) as usize].0;
+    }
+    rate
+}
+
+// brings an entry's claimable reward, stake, tranche tiers and time-weighted deposit up to `time`,
+// crediting `ratio` of what it earned
+pub fn settle_entry(campaign: &Campaign, entry: &mut Entry, time: u64, ratio: Decimal256) -> StdResult<Settlement> {
+    let mut settlement = Settlement {
+        accrued: Uint128::zero(),
+        promotions: vec![],
+    };
+    // unstaked MIN is claimable again once its cooldown is over
+    let mut released = Uint128::zero();
+    for unbonding in entry.unbonding.iter().filter(|unbonding| unbonding.release_time <= time) {
+        released = released.checked_add(unbonding.amount)?;
+    }
+    entry.unbonding.retain(|unbonding| unbonding.release_time > time);
+    entry.claimable_reward = entry.claimable_reward.checked_add(released)?;
+    if time > entry.last_reward_time {
+        settlement.accrued = to_uint128(Uint256::from(pending_reward(campaign, entry, time)?) * ratio)?;
+        for (tranche_id, tranche) in entry.dynamic_reward_log.iter_mut().enumerate() {
+            let new_tier = reward_tier(campaign, tranche, time);
+            if new_tier != tranche.reward_tier && !tranche.amount.is_zero() {
+                settlement.promotions.push(Promotion {
+                    tranche_id,
+                    old_tier: tranche.reward_tier,
+                    new_tier,
+                });
+            }
+            tranche.reward_tier = new_tier;
+        }
+        if entry.auto_compound {
+            entry.staked_reward = entry.staked_reward.checked_add(settlement.accrued)?;
+        } else {
+            entry.claimable_reward = entry.claimable_reward.checked_add(settlement.accrued)?;
+        }
+        entry.time_weighted_deposit = time_weighted_deposit(entry, time)?;
+        entry.reward_accrued = entry.reward_accrued.checked_add(settlement.accrued)?;
+        entry.last_reward_time = time;
+    }
+    refresh_averaged_rate(campaign, entry, time);
+    Ok(settlement)
+}
+
+// UST-seconds the entry has held up to `time`, the stored value only counts up to its last settle
+pub fn time_weighted_deposit(entry: &Entry, time: u64) -> StdResult<Uint128> {
+    let elapsed = Uint128::from(time.saturating_sub(entry.last_reward_time));
+    Ok(entry.time_weighted_deposit.checked_add(entry.ust_deposited.checked_mul(elapsed)?)?)
+}
+
+pub fn refresh_averaged_rate(campaign: &Campaign, entry: &mut Entry, time: u64) {
+    entry.averaged_reward_rate = averaged_reward_rate(campaign, &entry.dynamic_reward_log, time);
+}
+
+// MIN per UST per second the entry has earned over its whole life, boosts and throttling included
+pub fn lifetime_reward_rate(entry: &Entry) -> Decimal256 {
+    if entry.time_weighted_deposit.is_zero() {
+        return Decimal256::zero();
+    }
+    ratio(entry.reward_accrued, entry.time_weighted_deposit)
+}
@@ -0,0 +1,135 @@
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{Addr, Coin, Deps, Env, StdError, StdResult, Uint128};
+use cw_storage_plus::U64Key;
+
+use crate::handler::{anchor, partner, price, referral, reward};
+use crate::handler::execute::{depleted_tranches, none_deposit_helper, refund_aust, rewards_payable, sell_fill, some_deposit_helper, some_withdraw_helper};
+use crate::handler::math::to_uint128;
+use crate::msg::{SimulateClaimResponse, SimulateDepositResponse, SimulateSellResponse, SimulateWithdrawResponse};
+use crate::state::{campaign_key, entries, Campaign, CampaignStatus, Entry, DepletionOrder, CAMPAIGNS, REFERRERS};
+
+pub fn simulate_deposit(deps: Deps, env: Env, campaign_id: u64, entry_address: String, amount: Uint128) -> StdResult<SimulateDepositResponse> {
+    let campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let valid_address = deps.api.addr_validate(&entry_address)?;
+    let time = env.block.time.seconds();
+
+    // anchor receives the deposit after tax and mints aust at the current exchange rate
+    let tax_amount: Uint128 = anchor::compute_tax(deps, &Coin { denom: "uust".to_string(), amount })?.into();
+    let net_amount = amount.checked_sub(tax_amount)?;
+    let epoch_state = anchor::epoch_state(deps, campaign.anc_market.clone(), env.block.height)?;
+    let aust_minted = Uint256::from(net_amount) / epoch_state.exchange_rate;
+
+    let entry = match entries().may_load(deps.storage, campaign_key(campaign_id, &valid_address))? {
+        Some(mut entry) => {
+            settle_preview(deps, &campaign, &mut entry, &valid_address, time, true)?;
+            some_deposit_helper(entry, amount, time)?
+        },
+        None => none_deposit_helper(amount, time)?,
+    };
+    Ok(SimulateDepositResponse {
+        deposit_amount: amount,
+        tax_amount,
+        net_amount,
+        exchange_rate: epoch_state.exchange_rate,
+        aust_minted,
+        dynamic_reward_log: entry.dynamic_reward_log,
+    })
+}
+
+pub fn simulate_withdraw(deps: Deps, env: Env, campaign_id: u64, entry_address: String, amount: Uint128, order: Option<DepletionOrder>) -> StdResult<SimulateWithdrawResponse> {
+    let campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let valid_address = deps.api.addr_validate(&entry_address)?;
+    let time = env.block.time.seconds();
+    let mut entry = entries().load(deps.storage, campaign_key(campaign_id, &valid_address))?;
+    settle_preview(deps, &campaign, &mut entry, &valid_address, time, false)?;
+    let before = entry.dynamic_reward_log.clone();
+    let order = order.unwrap_or_else(|| campaign.depletion_order.clone());
+    let after = some_withdraw_helper(entry, time, amount, &order)
+        .map_err(|err| StdError::generic_err(err.to_string()))?
+        .dynamic_reward_log;
+
+    // redeemed ust is taxed once leaving anchor and once more on the way to the user,
+    // a failed campaign pays out the entry's share of its aust instead of the principal alone
+    let epoch_state = anchor::epoch_state(deps, campaign.anc_market.clone(), env.block.height)?;
+    let (aust_burned, ust_amount) = if campaign.status == CampaignStatus::Failed {
+        let aust_burned = refund_aust(&campaign, amount);
+        (aust_burned, to_uint128(aust_burned * epoch_state.exchange_rate)?)
+    } else {
+        (Uint256::from(amount) / epoch_state.exchange_rate, amount)
+    };
+    let redeemed = anchor::deduct_tax(deps, Coin { denom: "uust".to_string(), amount: ust_amount })?;
+    let received = anchor::deduct_tax(deps, redeemed)?;
+
+    Ok(SimulateWithdrawResponse {
+        withdraw_amount: amount,
+        tax_amount: ust_amount.checked_sub(received.amount)?,
+        net_amount: received.amount,
+        exchange_rate: epoch_state.exchange_rate,
+        aust_burned,
+        depleted_tranches: depleted_tranches(&before, &after),
+        dynamic_reward_log: after,
+    })
+}
+
+pub fn simulate_sell(deps: Deps, env: Env, campaign_id: u64, entry_address: String, amount: Uint128) -> StdResult<SimulateSellResponse> {
+    let campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let valid_address = deps.api.addr_validate(&entry_address)?;
+    rewards_payable(&campaign).map_err(|err| StdError::generic_err(err.to_string()))?;
+    let sellback_price = price::effective_price(deps, &env, &campaign)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+    let fill_amount = sell_fill(deps.storage, &campaign, &valid_address, env.block.time.seconds(), amount, sellback_price)?;
+    let ust_amount = price::sellback_amount(sellback_price, fill_amount)?;
+    let received = anchor::deduct_tax(deps, Coin { denom: "uust".to_string(), amount: ust_amount })?;
+    Ok(SimulateSellResponse {
+        sell_amount: amount,
+        fill_amount,
+        excess_amount: amount.checked_sub(fill_amount)?,
+        sellback_price,
+        ust_amount,
+        tax_amount: ust_amount.checked_sub(received.amount)?,
+        net_amount: received.amount,
+    })
+}
+
+pub fn simulate_claim(deps: Deps, env: Env, campaign_id: u64, entry_address: String) -> StdResult<SimulateClaimResponse> {
+    let campaign = CAMPAIGNS.load(deps.storage, U64Key::new(campaign_id))?;
+    let valid_address = deps.api.addr_validate(&entry_address)?;
+    let time = env.block.time.seconds();
+    // a failed campaign has voided every reward, there is nothing to claim
+    if campaign.status == CampaignStatus::Failed {
+        return Ok(SimulateClaimResponse {
+            claim_amount: Uint128::zero(),
+            dynamic_reward_log: entries().load(deps.storage, campaign_key(campaign_id, &valid_address))?.dynamic_reward_log,
+        });
+    }
+    rewards_payable(&campaign).map_err(|err| StdError::generic_err(err.to_string()))?;
+    let mut entry = entries().load(deps.storage, campaign_key(campaign_id, &valid_address))?;
+    settle_preview(deps, &campaign, &mut entry, &valid_address, time, true)?;
+    // a claim also pays out whatever referral bonus the entry has earned as a referrer
+    let referral_bonus = REFERRERS.may_load(deps.storage, campaign_key(campaign_id, &valid_address))?.unwrap_or_default().unclaimed;
+    Ok(SimulateClaimResponse {
+        claim_amount: entry.claimable_reward.checked_add(referral_bonus)?,
+        dynamic_reward_log: entry.dynamic_reward_log,
+    })
+}
+
+// settles a copy of the entry the way executing at `time` would, at the campaign's throttle ratio
+fn settle_preview(deps: Deps, campaign: &Campaign, entry: &mut Entry, address: &Addr, time: u64, query_partners: bool) -> StdResult<()> {
+    // nothing accrues once a campaign has failed
+    if campaign.status == CampaignStatus::Failed {
+        return Ok(());
+    }
+    let current = if query_partners {
+        partner::current_boost(deps, campaign.id, address)?
+    } else {
+        Decimal256::zero()
+    };
+    entry.partner_boost = partner::settled_boost(entry, current);
+    let mut campaign = campaign.clone();
+    reward::advance_outstanding(&mut campaign, time)?;
+    let referral_bonus = referral::pending_bonus(deps.storage, &campaign, entry, address, entry.last_reward_time, time)?;
+    let ratio = reward::throttle_ratio(&campaign, entry, referral_bonus, time)?;
+    reward::settle_entry(&campaign, entry, time, ratio)?;
+    entry.partner_boost = current;
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::{Api, StdResult, Uint128};
+
+use crate::ContractError;
+use crate::handler::math::to_uint128;
+use crate::state::{YieldRecipient, YieldShare};
+
+// every share pays something, no recipient is named twice and the shares add up to all of the yield
+pub fn validate_yield_split(split: &[YieldShare]) -> Result<(), ContractError> {
+    let mut total = Decimal256::zero();
+    for (index, share) in split.iter().enumerate() {
+        if share.share.is_zero() || split[..index].iter().any(|other| same_recipient(&other.recipient, &share.recipient)) {
+            return Err(ContractError::InvalidYieldSplit {});
+        }
+        total += share.share;
+    }
+    if total != Decimal256::one() {
+        return Err(ContractError::InvalidYieldSplit {});
+    }
+    Ok(())
+}
+
+// checks the shares and the fee collector's address
+pub fn validated_yield_split(api: &dyn Api, split: Vec<YieldShare>) -> Result<Vec<YieldShare>, ContractError> {
+    validate_yield_split(&split)?;
+    split
+        .into_iter()
+        .map(|share| {
+            let recipient = match share.recipient {
+                YieldRecipient::FeeCollector { address } => YieldRecipient::FeeCollector { address: api.addr_validate(address.as_str())? },
+                recipient => recipient,
+            };
+            Ok(YieldShare { recipient, share: share.share })
+        })
+        .collect()
+}
+
+fn same_recipient(a: &YieldRecipient, b: &YieldRecipient) -> bool {
+    std::mem::discriminant(a) == std::mem::discriminant(b)
+}
+
+// share of the yield kept back to pay for sellbacks
+pub fn reserve_share(split: &[YieldShare]) -> Decimal256 {
+    split
+        .iter()
+        .filter(|share| share.recipient == YieldRecipient::SellbackReserve {})
+        .fold(Decimal256::zero(), |total, share| total + share.share)
+}
+
+// what each recipient gets of `amount`, the last one takes the rounding dust so nothing is left over
+pub fn split_yield(split: &[YieldShare], amount: Uint128) -> StdResult<Vec<(YieldRecipient, Uint128)>> {
+    let mut remaining = amount;
+    let mut payouts = Vec::with_capacity(split.len());
+    for (index, share) in split.iter().enumerate() {
+        let payout = if index + 1 == split.len() {
+            remaining
+        } else {
+            to_uint128(Uint256::from(amount) * share.share)?.min(remaining)
+        };
+        remaining = remaining.checked_sub(payout)?;
+        payouts.push((share.recipient.clone(), payout));
+    }
+    Ok(payouts)
+}
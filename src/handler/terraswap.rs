@@ -0,0 +1,53 @@
+use cosmwasm_bignumber::Decimal256;
+use cosmwasm_std::*;
+
+use crate::handler::math::ratio;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    Pool {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PoolResponse {
+    pub assets: [Asset; 2],
+    pub total_share: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Asset {
+    pub info: AssetInfo,
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetInfo {
+    Token { contract_addr: String },
+    NativeToken { denom: String },
+}
+
+pub fn pool(deps: Deps, pair: String) -> StdResult<PoolResponse> {
+    deps.querier.query_wasm_smart(deps.api.addr_validate(&pair)?, &QueryMsg::Pool {})
+}
+
+// spot price of one `token` in `denom`
+pub fn spot_price(deps: Deps, pair: String, token: &Addr, denom: &str) -> StdResult<Decimal256> {
+    let pool = pool(deps, pair)?;
+    let reserve = |wanted: &dyn Fn(&AssetInfo) -> bool| {
+        pool.assets
+            .iter()
+            .find(|asset| wanted(&asset.info))
+            .map(|asset| asset.amount)
+            .ok_or_else(|| StdError::generic_err("Pair does not hold the expected assets"))
+    };
+    let token_reserve = reserve(&|info| matches!(info, AssetInfo::Token { contract_addr } if contract_addr == token.as_str()))?;
+    let denom_reserve = reserve(&|info| matches!(info, AssetInfo::NativeToken { denom: native } if native == denom))?;
+    if token_reserve.is_zero() {
+        return Err(StdError::generic_err("Pair has no liquidity"));
+    }
+    Ok(ratio(denom_reserve, token_reserve))
+}
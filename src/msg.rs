@@ -1,73 +1,434 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use cosmwasm_std::{Addr, Uint128};
-use crate::state::{Deposit, Withdraw, Reward};
+use cw20::Cw20ReceiveMsg;
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use crate::ContractError;
+use crate::handler::{execute, price, reward, split};
+use crate::state::{BoostBracket, CampaignStatus, DepletionOrder, Deposit, PartnerKind, PriceSource, Referral, Withdraw, Reward, RewardMode, Unbonding, YieldShare};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
+    /// runs the campaign, the sender when unset
+    pub owner: Option<String>,
     pub treasury_wallet: Addr,
     pub reward_contract: Addr,
+    pub reward_token: String,
+    pub reward_mode: RewardMode,
+    /// principal counted from the start, has to be zero with a soft cap
     pub ust_deposited: Uint128,
-    pub sellback_price: u64,
+    /// UST paid per MIN sold back
+    pub sellback_price: Decimal256,
     pub anc_market: String,
     pub aust_contract: String,
-    pub tier0rate: u64,
+    /// MIN earned per UST deposited per second
+    pub tier0rate: Decimal256,
+    /// seconds after deposit a tranche enters the tier
     pub tier0time: u64,
-    pub tier1rate: u64,
+    /// MIN earned per UST deposited per second
+    pub tier1rate: Decimal256,
+    /// seconds after deposit a tranche enters the tier
     pub tier1time: u64,
-    pub tier2rate: u64,
+    /// MIN earned per UST deposited per second
+    pub tier2rate: Decimal256,
+    /// seconds after deposit a tranche enters the tier
     pub tier2time: u64,
-    pub tier3rate: u64,
+    /// MIN earned per UST deposited per second
+    pub tier3rate: Decimal256,
+    /// seconds after deposit a tranche enters the tier
     pub tier3time: u64,
+    /// deposits are taken from this time on, from the start when unset
+    pub start_time: Option<u64>,
+    /// deposits are no longer taken from this time on, open-ended when unset
+    pub end_time: Option<u64>,
+    /// UST the campaign takes in deposits at most, uncapped when unset
+    pub deposit_cap: Option<Uint128>,
+    /// UST the campaign has to take in by `deadline` to succeed, set together with it
+    pub soft_cap: Option<Uint128>,
+    /// the campaign can be finalized against its soft cap from this time on
+    pub deadline: Option<u64>,
+    /// where yield cashouts go, all of it to the treasury when unset
+    pub yield_split: Option<Vec<YieldShare>>,
+}
+
+impl InstantiateMsg {
+    // the checks a campaign's terms have to pass, so a template can be vetted before it is used
+    pub fn validate(&self) -> Result<(), ContractError> {
+        reward::validate_tier_schedule(&[
+            (self.tier0rate, self.tier0time),
+            (self.tier1rate, self.tier1time),
+            (self.tier2rate, self.tier2time),
+            (self.tier3rate, self.tier3time),
+        ])?;
+        price::validate_sellback_price(self.sellback_price)?;
+        if let Some(start_time) = self.start_time {
+            execute::validate_campaign_window(start_time, self.end_time)?;
+        }
+        execute::validate_success_criteria(self.start_time.unwrap_or_default(), self.soft_cap, self.deadline)?;
+        // principal seeded at instantiation has no deposit behind it to refund if the raise fails
+        if self.soft_cap.is_some() && !self.ust_deposited.is_zero() {
+            return Err(ContractError::SoftCapWithDeposits {});
+        }
+        if let Some(yield_split) = &self.yield_split {
+            split::validate_yield_split(yield_split)?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
-    DepositUst { entry_address: String, amount: Uint128 },
-    WithdrawUst { entry_address: String, amount: Uint128 },
-    ClaimReward { entry_address: String },
-    SellReward { entry_address: String, amount: Uint128 },
-    UpdateEntries {},
-    CashoutYield {},
-    SetTreasuryWallet { address: String },
-    SetRewardContract { address: String },
-    SetTierData { data: (u8, u64, u64) },
-    SetAncMarket { address: Addr },
+    // opens another raise next to the existing ones, on the same terms instantiate takes
+    CreateCampaign(Box<InstantiateMsg>),
+    DepositUst { campaign_id: u64, entry_address: String, amount: Uint128, referrer: Option<String> },
+    // not available once a receipt token is registered, receipts are redeemed through its Send instead
+    WithdrawUst { campaign_id: u64, entry_address: String, amount: Uint128, order: Option<DepletionOrder> },
+    ClaimReward { campaign_id: u64, entry_address: String },
+    SellReward { campaign_id: u64, entry_address: String, amount: Uint128, on_excess: Option<SellExcess> },
+    UpdateEntries { campaign_id: u64, start_after: Option<String>, limit: Option<u32> },
+    CashoutYield { campaign_id: u64 },
+    FundRewards { campaign_id: u64, amount: Uint128 },
+    SetTreasuryWallet { campaign_id: u64, address: String },
+    SetRewardContract { campaign_id: u64, address: String },
+    SetRewardToken { campaign_id: u64, address: String },
+    SetRewardMode { campaign_id: u64, mode: RewardMode },
+    SetTierData { campaign_id: u64, data: (u8, Decimal256, u64) },
+    SetAncMarket { campaign_id: u64, address: Addr },
+    SetSellbackPrice { campaign_id: u64, price: Decimal256 },
+    SetSellbackSource { campaign_id: u64, source: PriceSource, floor: Decimal256, ceiling: Option<Decimal256> },
+    // owner only, observations are at least a thirtieth of the twap window apart
+    RecordSellbackPrice { campaign_id: u64 },
+    SetYieldSplit { campaign_id: u64, split: Vec<YieldShare> },
+    SetSellLimits { campaign_id: u64, epoch_length: u64, global_cap: Option<Uint128>, address_cap: Option<Uint128> },
+    ProcessSellQueue { campaign_id: u64, start_after: Option<u64>, limit: Option<u32> },
+    CancelQueuedSell { campaign_id: u64, id: u64 },
+    SetReferralTerms { campaign_id: u64, share: Decimal256, period: u64 },
+    SetAutoCompound { campaign_id: u64, entry_address: String, enabled: bool },
+    StakeReward { campaign_id: u64, entry_address: String, amount: Uint128 },
+    UnstakeReward { campaign_id: u64, entry_address: String, amount: Uint128 },
+    SetStakeTerms { campaign_id: u64, boost_rate: Decimal256, boost_cap: Decimal256, unstake_cooldown: u64 },
+    SetPartnerBoost { campaign_id: u64, contract: String, kind: PartnerKind, brackets: Vec<BoostBracket> },
+    RemovePartnerBoost { campaign_id: u64, contract: String },
+    // the receipt token has to be a cw20 this contract mints that sends ReceiptTransferred here whenever receipts
+    // change hands, like the ipr-receipt contract, and can only be registered before anyone deposits
+    RegisterDpToken { campaign_id: u64, address: String },
+    Receive(Cw20ReceiveMsg),
+    // sent by the receipt token whenever receipts change hands
+    ReceiptTransferred { campaign_id: u64, from: String, to: String, amount: Uint128 },
+    TransferEntry { campaign_id: u64, to: String, amount: Uint128 },
+    SetDepletionOrder { campaign_id: u64, order: DepletionOrder },
+    SetCampaignWindow { campaign_id: u64, start_time: u64, end_time: Option<u64> },
+    SetDepositCap { campaign_id: u64, cap: Option<Uint128> },
+    SetSuccessCriteria { campaign_id: u64, soft_cap: Option<Uint128>, deadline: Option<u64> },
+    // settles a campaign with a soft cap once its deadline has passed, anyone can send it
+    Finalize { campaign_id: u64 },
+}
+
+// what happens to the part of a sell over the epoch caps, rejected unless asked otherwise
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SellExcess {
+    Reject,
+    Queue,
+}
+
+// hooks the receipt token can send along with receipts
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw20HookMsg {
+    Redeem { campaign_id: u64 },
+}
+
+// interface the reward contract implements to pay out claims on this contract's behalf
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RewardContractMsg {
+    DistributeReward { recipient: String, amount: Uint128 },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
-    GetEntry { entry_address: String },
-    GetState {},
+    GetEntry { campaign_id: u64, entry_address: String },
+    GetConfig {},
+    GetCampaign { campaign_id: u64 },
+    ListCampaigns { start_after: Option<u64>, limit: Option<u32> },
+    SimulateDeposit { campaign_id: u64, entry_address: String, amount: Uint128 },
+    SimulateWithdraw { campaign_id: u64, entry_address: String, amount: Uint128, order: Option<DepletionOrder> },
+    SimulateSell { campaign_id: u64, entry_address: String, amount: Uint128 },
+    SimulateClaim { campaign_id: u64, entry_address: String },
+    GetProjectedRewards { campaign_id: u64, entry_address: String, time: u64 },
+    GetStats { campaign_id: u64 },
+    GetRewardBudget { campaign_id: u64 },
+    GetSellbackPrice { campaign_id: u64 },
+    GetSellbackReserve { campaign_id: u64 },
+    GetSellAllowance { campaign_id: u64, entry_address: String },
+    GetSellQueue { campaign_id: u64, start_after: Option<u64>, limit: Option<u32> },
+    GetReferral { campaign_id: u64, entry_address: String },
+    GetReferrer { campaign_id: u64, address: String },
+    GetPartnerBoosts { campaign_id: u64 },
+    GetDeposited { campaign_id: u64, entry_address: String, height: Option<u64> },
+    GetTotalDeposited { campaign_id: u64, height: Option<u64> },
+    // the time-weighted ranking uses each entry's value as of its last settle, UpdateEntries brings
+    // a page of entries up to date
+    TopDepositors { campaign_id: u64, limit: Option<u32>, by: DepositorRanking },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DepositorRanking {
+    UstDeposited,
+    TimeWeightedDeposit,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct EntryResponse {
     pub claimable_reward: Uint128,
     pub ust_deposited: Uint128,
-    pub averaged_reward_rate: u64,
+    pub averaged_reward_rate: Decimal256,
+    pub lifetime_reward_rate: Decimal256,
+    pub reward_accrued: Uint128,
     pub ust_deposit_log: Vec<Deposit>,
     pub ust_withdraw_log: Vec<Withdraw>,
     pub dynamic_reward_log: Vec<Reward>,
+    pub auto_compound: bool,
+    pub staked_reward: Uint128,
+    pub unbonding: Vec<Unbonding>,
+    pub stake_boost: Decimal256,
+    pub partner_boosts: Vec<ActivePartnerBoost>,
+    pub reward_boost: Decimal256,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct StateResponse {
+pub struct ActivePartnerBoost {
+    pub contract: Addr,
+    pub boost: Decimal256,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigResponse {
+    pub owner: Addr,
+    pub campaign_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CampaignsResponse {
+    pub campaigns: Vec<CampaignResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CampaignResponse {
+    pub id: u64,
     pub owner: Addr,
     pub treasury_wallet: Addr,
+    pub yield_split: Vec<YieldShare>,
     pub reward_contract: Addr,
+    pub reward_token: Addr,
+    pub reward_mode: RewardMode,
+    pub dp_token: Option<Addr>,
     pub ust_deposited: Uint128,
-    pub sellback_price: u64,
+    pub start_time: u64,
+    pub end_time: Option<u64>,
+    pub deposit_cap: Option<Uint128>,
+    pub soft_cap: Option<Uint128>,
+    pub deadline: Option<u64>,
+    pub status: CampaignStatus,
+    pub depletion_order: DepletionOrder,
+    pub sellback_price: Decimal256,
     pub anc_market: String,
     pub aust_contract: String,
-    pub tier0rate: u64,
+    pub tier0rate: Decimal256,
     pub tier0time: u64,
-    pub tier1rate: u64,
+    pub tier1rate: Decimal256,
     pub tier1time: u64,
-    pub tier2rate: u64,
+    pub tier2rate: Decimal256,
     pub tier2time: u64,
-    pub tier3rate: u64,
+    pub tier3rate: Decimal256,
     pub tier3time: u64,
-}
\ No newline at end of file
+}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SimulateDepositResponse {
+    pub deposit_amount: Uint128,
+    pub tax_amount: Uint128,
+    pub net_amount: Uint128,
+    pub exchange_rate: Decimal256,
+    pub aust_minted: Uint256,
+    pub dynamic_reward_log: Vec<Reward>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SimulateWithdrawResponse {
+    pub withdraw_amount: Uint128,
+    pub tax_amount: Uint128,
+    pub net_amount: Uint128,
+    pub exchange_rate: Decimal256,
+    pub aust_burned: Uint256,
+    pub depleted_tranches: Vec<Reward>,
+    pub dynamic_reward_log: Vec<Reward>,
+}
+
+// set as the data of a withdrawal's response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WithdrawResponse {
+    pub depletion_order: DepletionOrder,
+    pub depleted_tranches: Vec<Reward>,
+    pub dynamic_reward_log: Vec<Reward>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SimulateSellResponse {
+    pub sell_amount: Uint128,
+    // what fills now, the rest is over this epoch's caps or the reserve and gets rejected or queued
+    pub fill_amount: Uint128,
+    pub excess_amount: Uint128,
+    pub sellback_price: Decimal256,
+    pub ust_amount: Uint128,
+    pub tax_amount: Uint128,
+    pub net_amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SimulateClaimResponse {
+    pub claim_amount: Uint128,
+    pub dynamic_reward_log: Vec<Reward>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ProjectedRewardsResponse {
+    pub time: u64,
+    pub claimable_reward: Uint128,
+    pub projected_reward: Uint128,
+    pub averaged_reward_rate: Decimal256,
+    pub tranches: Vec<TrancheProjection>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TrancheProjection {
+    pub amount: Uint128,
+    pub time: u64,
+    pub reward_tier: u8,
+    pub projected_reward: Uint128,
+    pub promotions: Vec<TierPromotion>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TierPromotion {
+    pub reward_tier: u8,
+    pub time: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StatsResponse {
+    pub aust_balance: Uint128,
+    pub exchange_rate: Decimal256,
+    pub total_value_locked: Uint256,
+    pub ust_deposited: Uint128,
+    pub unrealized_yield: Uint256,
+    pub yield_cashed_out: Uint128,
+    pub sellback_reserve: Uint128,
+    pub total_reward_accrued: Uint128,
+    pub total_reward_claimed: Uint128,
+    pub total_reward_bought_back: Uint128,
+    pub depositor_count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DepositedResponse {
+    pub ust_deposited: Uint128,
+    pub height: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TopDepositorsResponse {
+    pub depositors: Vec<DepositorRank>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DepositorRank {
+    pub address: Addr,
+    pub ust_deposited: Uint128,
+    pub time_weighted_deposit: Uint128,
+    // when time_weighted_deposit was last brought up to date
+    pub settled_time: u64,
+    // the time-weighted deposit as of the query
+    pub current_time_weighted_deposit: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RewardBudgetResponse {
+    pub reward_budget: Uint128,
+    pub total_reward_accrued: Uint128,
+    pub pending_reward: Uint128,
+    pub remaining_budget: Uint128,
+    pub emission_rate: Decimal256,
+    pub estimated_exhaustion_time: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SellbackPriceResponse {
+    pub source: PriceSource,
+    pub source_price: Decimal256,
+    pub floor: Decimal256,
+    pub ceiling: Option<Decimal256>,
+    pub effective_price: Decimal256,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SellbackReserveResponse {
+    pub reserve: Uint128,
+    pub reserve_share: Decimal256,
+    pub outstanding_reward: Uint128,
+    pub sellback_price: Decimal256,
+    pub liability: Uint128,
+    pub coverage: Option<Decimal256>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SellAllowanceResponse {
+    pub epoch: u64,
+    pub epoch_end: u64,
+    pub global_remaining: Option<Uint128>,
+    pub address_remaining: Option<Uint128>,
+    pub available: Option<Uint128>,
+    pub queued: Uint128,
+    pub queued_sells: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SellQueueResponse {
+    pub sells: Vec<QueuedSellResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct QueuedSellResponse {
+    pub id: u64,
+    pub entry_address: Addr,
+    pub amount: Uint128,
+    pub time: u64,
+    pub reward_token: Addr,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReferralResponse {
+    pub referral: Option<Referral>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReferrerResponse {
+    pub referees: u64,
+    pub earned: Uint128,
+    pub unclaimed: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PartnerBoostsResponse {
+    pub partners: Vec<PartnerBoostResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PartnerBoostResponse {
+    pub contract: Addr,
+    pub kind: PartnerKind,
+    pub brackets: Vec<BoostBracket>,
+}
@@ -1,35 +1,184 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use cosmwasm_std::{Addr, Uint128};
-use cw_storage_plus::{Item, Map};
+use cosmwasm_std::{Addr, StdError, StdResult, Uint128};
+use cosmwasm_bignumber::Decimal256;
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex, SnapshotMap, Strategy, U128Key, U64Key};
 
+// who can open new campaigns and how many have been opened, ids count up from zero
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct State {
+pub struct Config {
+    pub owner: Addr,
+    pub campaign_count: u64,
+}
+
+// one raise, with its own terms, entries and accounting
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Campaign {
+    pub id: u64,
     pub owner: Addr,
     pub treasury_wallet: Addr,
+    /// where each yield cashout goes, the shares add up to one
+    pub yield_split: Vec<YieldShare>,
     pub reward_contract: Addr,
+    pub reward_token: Addr,
+    pub reward_mode: RewardMode,
+    /// receipt token minted 1:1 for deposited principal, unset until registered
+    pub dp_token: Option<Addr>,
     pub ust_deposited: Uint128,
-    pub sellback_price: u64,
+    /// deposits are taken from this time on
+    pub start_time: u64,
+    /// deposits are no longer taken from this time on, open-ended when unset
+    pub end_time: Option<u64>,
+    /// UST the campaign takes in deposits at most, uncapped when unset
+    pub deposit_cap: Option<Uint128>,
+    /// UST the campaign has to take in by `deadline` to succeed, no target when unset
+    pub soft_cap: Option<Uint128>,
+    /// the campaign can be finalized against its soft cap from this time on
+    pub deadline: Option<u64>,
+    pub status: CampaignStatus,
+    /// aUST bought with the campaign's deposits, less what its withdrawals and cashouts redeemed
+    pub aust_balance: Uint128,
+    /// which tranches a withdrawal draws down first unless the call says otherwise
+    pub depletion_order: DepletionOrder,
+    /// UST paid per MIN sold back
+    pub sellback_price: Decimal256,
+    pub sellback_source: PriceSource,
+    /// UST per MIN
+    pub sellback_floor: Decimal256,
+    /// UST per MIN
+    pub sellback_ceiling: Option<Decimal256>,
     pub anc_market: String,
     pub aust_contract: String,
-    pub tier0rate: u64,
+    /// MIN earned per UST deposited per second
+    pub tier0rate: Decimal256,
+    /// seconds after deposit a tranche enters the tier
     pub tier0time: u64,
-    pub tier1rate: u64,
+    /// MIN earned per UST deposited per second
+    pub tier1rate: Decimal256,
+    /// seconds after deposit a tranche enters the tier
     pub tier1time: u64,
-    pub tier2rate: u64,
+    /// MIN earned per UST deposited per second
+    pub tier2rate: Decimal256,
+    /// seconds after deposit a tranche enters the tier
     pub tier2time: u64,
-    pub tier3rate: u64,
+    /// MIN earned per UST deposited per second
+    pub tier3rate: Decimal256,
+    /// seconds after deposit a tranche enters the tier
     pub tier3time: u64,
+    /// schedules the tiers were on before, each in force until its checkpoint, oldest first
+    pub tier_history: Vec<TierCheckpoint>,
+    pub yield_cashed_out: Uint128,
+    /// UST held by this contract to pay for sellbacks
+    pub sellback_reserve: Uint128,
+    /// seconds in each sell epoch the caps below apply to
+    pub sell_epoch_length: u64,
+    /// MIN all holders together can sell back per epoch, uncapped when unset
+    pub sell_global_cap: Option<Uint128>,
+    /// MIN a single holder can sell back per epoch, uncapped when unset
+    pub sell_address_cap: Option<Uint128>,
+    /// share of a referee's MIN accrual paid to its referrer as a bonus
+    pub referral_share: Decimal256,
+    /// seconds after the first deposit the referrer earns the bonus for
+    pub referral_period: u64,
+    /// reward boost per MIN staked per UST deposited
+    pub stake_boost_rate: Decimal256,
+    /// most boost a stake can add on top of the base reward
+    pub stake_boost_cap: Decimal256,
+    /// seconds unstaked MIN waits before it becomes claimable
+    pub unstake_cooldown: u64,
+    pub reward_budget: Uint128,
+    /// principal the entries hold at each tier as of their last settle, boosts and referral shares included
+    pub tier_principal: [Decimal256; 4],
+    /// MIN earned per unit of principal at each tier up to `outstanding_time`
+    pub tier_index: [Decimal256; 4],
+    /// MIN the entries and their referrers have earned and not yet settled, estimated at their `tier_principal`
+    pub outstanding_reward: Uint128,
+    /// time `outstanding_reward` was last brought up to
+    pub outstanding_time: u64,
+    pub total_reward_accrued: Uint128,
+    pub total_reward_claimed: Uint128,
+    pub total_reward_bought_back: Uint128,
+    pub depositor_count: u64,
+}
+
+// how claimed MIN reaches the entry's wallet: minted by this contract as a minter of the
+// reward token, transferred out of a balance pre-funded to this contract, or paid out by
+// the reward contract and confirmed back through a reply
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RewardMode {
+    Mint,
+    Transfer,
+    Delegate,
+}
+
+// where a share of the harvested yield goes: the campaign's treasury wallet, the sellback reserve
+// this contract holds, a platform fee collector, or burnt
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum YieldRecipient {
+    Treasury {},
+    SellbackReserve {},
+    FeeCollector { address: Addr },
+    Burn {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct YieldShare {
+    pub recipient: YieldRecipient,
+    /// share of the yield, all shares add up to one
+    pub share: Decimal256,
+}
+
+// a campaign with a soft cap stays active until it is finalized after its deadline, a failed
+// one voids its rewards and hands its aUST back to the entries pro-rata
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CampaignStatus {
+    Active,
+    Succeeded,
+    Failed,
+}
+
+// a tier schedule that was replaced, with the time it stopped applying
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TierCheckpoint {
+    /// (rate, time) of each tier
+    pub schedule: [(Decimal256, u64); 4],
+    pub until: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Entry {
     pub claimable_reward: Uint128,
     pub ust_deposited: Uint128,
-    pub averaged_reward_rate: u64,
+    /// MIN per UST per second
+    pub averaged_reward_rate: Decimal256,
     pub ust_deposit_log: Vec<Deposit>,
     pub ust_withdraw_log: Vec<Withdraw>,
     pub dynamic_reward_log: Vec<Reward>,
+    pub last_reward_time: u64,
+    pub time_weighted_deposit: Uint128,
+    /// MIN credited to the entry over its life, staked or not
+    pub reward_accrued: Uint128,
+    pub auto_compound: bool,
+    pub staked_reward: Uint128,
+    pub unbonding: Vec<Unbonding>,
+    /// boost from the staked MIN as of the last time the entry was saved, applied until it is next saved
+    pub stake_boost: Decimal256,
+    /// boost from partner holdings as of the last time the entry accrued, applied until it next accrues
+    pub partner_boost: Decimal256,
+    /// principal the entry counts for at each tier in the campaign's `tier_principal`
+    pub tier_principal: [Decimal256; 4],
+    /// the campaign's `tier_index` when the entry last settled
+    pub tier_index: [Decimal256; 4],
+}
+
+// unstaked MIN cooling down before it returns to claimable reward
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Unbonding {
+    pub amount: Uint128,
+    pub release_time: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -51,5 +200,173 @@ pub struct Reward {
     pub reward_tier: u8,
 }
 
-pub const STATE: Item<State> = Item::new("state");
-pub const ENTRIES: Map<&Addr, Entry> = Map::new("entries");
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DepletionOrder {
+    OldestFirst,
+    NewestFirst,
+    LowestTierFirst,
+}
+
+// where the sellback price comes from: the static sellback_price, the spot price of a
+// Terraswap-style MIN/UST pair, or a time-weighted average of that pair kept by this contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceSource {
+    Static {},
+    Pair { pair: Addr },
+    Twap { pair: Addr, window: u64, max_staleness: u64 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PriceObservation {
+    pub time: u64,
+    pub price: Decimal256,
+}
+
+// a claim sent to the reward contract, handed to its reply in the same transaction so claimable
+// reward is only zeroed once the payout succeeded
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingClaim {
+    pub campaign_id: u64,
+    pub entry_address: Addr,
+    pub amount: Uint128,
+}
+
+// MIN sold back during an epoch, stale once the epoch has passed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SellEpoch {
+    pub epoch: u64,
+    pub sold: Uint128,
+}
+
+// the part of a sell that went over the caps, held by this contract until the queue is processed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct QueuedSell {
+    pub entry_address: Addr,
+    pub amount: Uint128,
+    pub time: u64,
+    /// the token the MIN was pulled in, handed back in it if the sell is cancelled
+    pub reward_token: Addr,
+}
+
+// what an address has waiting in the sell queue
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct QueuedTotal {
+    pub amount: Uint128,
+    pub sells: u32,
+}
+
+// a referee's referrer and the terms in force when it first deposited
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Referral {
+    pub referrer: Addr,
+    pub share: Decimal256,
+    pub start: u64,
+    pub end: u64,
+    pub earned: Uint128,
+}
+
+// referral bonus a referrer has earned, unclaimed until its next claim
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct ReferrerInfo {
+    pub referees: u64,
+    pub earned: Uint128,
+    pub unclaimed: Uint128,
+}
+
+// a partner token whose holders earn a boost, the best bracket their holding reaches applies
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PartnerBoost {
+    pub kind: PartnerKind,
+    pub brackets: Vec<BoostBracket>,
+}
+
+// a CW20 is held by balance, a CW721 by the number of tokens owned
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PartnerKind {
+    Cw20,
+    Cw721,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BoostBracket {
+    pub min_holding: Uint128,
+    /// added to the reward multiplier, 0.1 pays 10% more
+    pub boost: Decimal256,
+}
+
+pub const CONFIG: Item<Config> = Item::new("config");
+pub const CAMPAIGNS: Map<U64Key, Campaign> = Map::new("campaigns");
+pub const PENDING_CLAIM: Item<PendingClaim> = Item::new("pending_claim");
+pub const PRICE_OBSERVATIONS: Map<U64Key, Vec<PriceObservation>> = Map::new("price_observations");
+pub const SELL_EPOCHS: Map<U64Key, SellEpoch> = Map::new("sell_epochs");
+pub const ADDRESS_SELL_EPOCHS: Map<(U64Key, &Addr), SellEpoch> = Map::new("address_sell_epochs");
+pub const SELL_QUEUE: Map<(U64Key, U64Key), QueuedSell> = Map::new("sell_queue");
+pub const SELL_QUEUE_NEXT_ID: Item<u64> = Item::new("sell_queue_next_id");
+// MIN and sells each address has waiting in the sell queue, kept alongside the queue so it never needs scanning
+pub const QUEUED_SELLS: Map<(U64Key, &Addr), QueuedTotal> = Map::new("queued_sells");
+pub const REFERRALS: Map<(U64Key, &Addr), Referral> = Map::new("referrals");
+pub const REFERRERS: Map<(U64Key, &Addr), ReferrerInfo> = Map::new("referrers");
+pub const PARTNER_BOOSTS: Map<(U64Key, &Addr), PartnerBoost> = Map::new("partner_boosts");
+
+// entries are indexed by principal and by time-weighted principal (UST seconds) for ranking within a campaign
+pub struct EntryIndexes<'a> {
+    pub ust_deposited: MultiIndex<'a, (U64Key, U128Key, Vec<u8>), Entry>,
+    pub time_weighted_deposit: MultiIndex<'a, (U64Key, U128Key, Vec<u8>), Entry>,
+}
+
+impl<'a> IndexList<Entry> for EntryIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Entry>> + '_> {
+        let v: Vec<&dyn Index<Entry>> = vec![&self.ust_deposited, &self.time_weighted_deposit];
+        Box::new(v.into_iter())
+    }
+}
+
+pub fn entries<'a>() -> IndexedMap<'a, (U64Key, &'a Addr), Entry, EntryIndexes<'a>> {
+    let indexes = EntryIndexes {
+        ust_deposited: MultiIndex::new(
+            |entry: &Entry, pk| (entry_campaign(&pk), U128Key::new(entry.ust_deposited.u128()), pk),
+            "entries",
+            "entries__ust_deposited",
+        ),
+        time_weighted_deposit: MultiIndex::new(
+            |entry: &Entry, pk| (entry_campaign(&pk), U128Key::new(entry.time_weighted_deposit.u128()), pk),
+            "entries",
+            "entries__time_weighted_deposit",
+        ),
+    };
+    IndexedMap::new("entries", indexes)
+}
+
+// per-address records are kept apart for each campaign
+pub fn campaign_key(campaign_id: u64, address: &Addr) -> (U64Key, &Addr) {
+    (U64Key::new(campaign_id), address)
+}
+
+// an entry's primary key is the length-prefixed campaign id followed by the address
+const ENTRY_KEY_PREFIX_LEN: usize = 10;
+
+fn entry_campaign(pk: &[u8]) -> U64Key {
+    U64Key::from(pk[2..ENTRY_KEY_PREFIX_LEN].to_vec())
+}
+
+pub fn entry_address(pk: &[u8]) -> StdResult<Addr> {
+    let address = pk.get(ENTRY_KEY_PREFIX_LEN..).ok_or_else(|| StdError::generic_err("Invalid entry key"))?;
+    Ok(Addr::unchecked(String::from_utf8(address.to_vec()).map_err(StdError::from)?))
+}
+
+// deposited principal checkpointed every block, readable at any past height
+pub const TOTAL_DEPOSITED: SnapshotMap<U64Key, Uint128> = SnapshotMap::new(
+    "total_deposited",
+    "total_deposited__checkpoints",
+    "total_deposited__changelog",
+    Strategy::EveryBlock,
+);
+pub const DEPOSITED: SnapshotMap<(U64Key, &Addr), Uint128> = SnapshotMap::new(
+    "deposited",
+    "deposited__checkpoints",
+    "deposited__changelog",
+    Strategy::EveryBlock,
+);
@@ -1,76 +1,1741 @@
-//use crate::contract;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
 
-//use cosmwasm_std::{from_binary, to_binary};
+use cosmwasm_bignumber::{Decimal256, Uint256};
+use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{coins, from_binary, from_slice, to_binary, Addr, Binary, ContractResult, CosmosMsg, Decimal, Env, BankMsg, OwnedDeps, Querier, QuerierResult, QueryRequest, Reply, ReplyOn, Response, SubMsgExecutionResponse, SystemError, SystemResult, Timestamp, Uint128, WasmMsg, WasmQuery};
+use cw20::{BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, MinterResponse};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use terra_cosmwasm::{TaxCapResponse, TaxRateResponse, TerraQuery, TerraQueryWrapper};
 
-/* 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    use cosmwasm_std::{coins, from_binary};
+use crate::contract::{execute, instantiate, query, reply};
+use crate::handler::anchor::EpochStateResponse;
+use crate::handler::execute::CLAIM_REPLY_ID;
+use crate::handler::split;
+use crate::handler::terraswap::{Asset, AssetInfo, PoolResponse};
+use crate::handler::partner::{Cw721QueryMsg, TokensResponse};
+use crate::msg::{CampaignResponse, Cw20HookMsg, DepositedResponse, DepositorRanking, EntryResponse, ExecuteMsg, InstantiateMsg, ProjectedRewardsResponse, QueryMsg, ReferralResponse, ReferrerResponse, RewardBudgetResponse, RewardContractMsg, SellAllowanceResponse, SellExcess, SellbackPriceResponse, SellbackReserveResponse, SimulateClaimResponse, SimulateDepositResponse, SimulateSellResponse, SimulateWithdrawResponse, StatsResponse, TopDepositorsResponse, WithdrawResponse};
+use crate::state::{BoostBracket, CampaignStatus, DepletionOrder, PartnerKind, PriceSource, Reward, RewardMode, YieldRecipient, YieldShare, PRICE_OBSERVATIONS};
+use crate::ContractError;
 
-    #[test]
-    fn proper_initialization() {
-        let mut deps = mock_dependencies(&[]);
+const OWNER: &str = "owner";
+const ANC_MARKET: &str = "anchor_market";
+const AUST: &str = "aust_token";
+const REWARD_TOKEN: &str = "min_token";
+const PARTNER: &str = "partner_token";
+const DP_TOKEN: &str = "dp_token";
+const PAIR: &str = "min_ust_pair";
+const START: u64 = 1_000_000;
 
-        let msg = contract::InstantiateMsg { };
-        let info = mock_info("creator", &coins(1000, "earth"));
+// what the other contracts on the mock chain answer
+struct Chain {
+    exchange_rate: Decimal256,
+    // (cw20 contract, holder) balances
+    balances: HashMap<(String, String), Uint128>,
+    // (cw721 contract, owner) token counts
+    nfts: HashMap<(String, String), u32>,
+    minters: HashMap<String, String>,
+    // terraswap pair -> (MIN, UST) reserves
+    pools: HashMap<String, (u128, u128)>,
+    broken: Vec<String>,
+}
+
+struct ChainQuerier {
+    base: MockQuerier<TerraQueryWrapper>,
+    chain: Rc<RefCell<Chain>>,
+}
+
+type Deps = OwnedDeps<MockStorage, MockApi, ChainQuerier>;
+
+fn respond<T: Serialize>(value: &T) -> QuerierResult {
+    SystemResult::Ok(ContractResult::Ok(to_binary(value).unwrap()))
+}
+
+fn unsupported(request: &[u8]) -> QuerierResult {
+    SystemResult::Err(SystemError::InvalidRequest {
+        error: "unsupported query".to_string(),
+        request: request.into(),
+    })
+}
+
+impl Querier for ChainQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        let request: QueryRequest<TerraQueryWrapper> = match from_slice(bin_request) {
+            Ok(request) => request,
+            Err(_) => return unsupported(bin_request),
+        };
+        match &request {
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => self.smart(contract_addr, msg),
+            // no tax, so amounts go through untouched
+            QueryRequest::Custom(TerraQueryWrapper { query_data: TerraQuery::TaxRate {}, .. }) => respond(&TaxRateResponse { rate: Decimal::zero() }),
+            QueryRequest::Custom(TerraQueryWrapper { query_data: TerraQuery::TaxCap { .. }, .. }) => respond(&TaxCapResponse { cap: Uint128::zero() }),
+            _ => self.base.handle_query(&request),
+        }
+    }
+}
 
-        // we can just call .unwrap() to assert this was a success
-        let res = contract::instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
-        assert_eq!(0, res.messages.len());
+impl ChainQuerier {
+    fn smart(&self, contract: &str, msg: &Binary) -> QuerierResult {
+        let chain = self.chain.borrow();
+        if chain.broken.iter().any(|broken| broken == contract) {
+            return SystemResult::Ok(ContractResult::Err("contract is broken".to_string()));
+        }
+        if contract == ANC_MARKET {
+            return respond(&EpochStateResponse {
+                exchange_rate: chain.exchange_rate,
+                aterra_supply: Uint256::zero(),
+            });
+        }
+        if let Some((min, ust)) = chain.pools.get(contract) {
+            return respond(&PoolResponse {
+                assets: [
+                    Asset { info: AssetInfo::Token { contract_addr: REWARD_TOKEN.to_string() }, amount: Uint128::from(*min) },
+                    Asset { info: AssetInfo::NativeToken { denom: "uust".to_string() }, amount: Uint128::from(*ust) },
+                ],
+                total_share: Uint128::zero(),
+            });
+        }
+        if let Ok(query) = from_binary::<Cw20QueryMsg>(msg) {
+            return match query {
+                Cw20QueryMsg::Balance { address } => respond(&BalanceResponse {
+                    balance: chain.balances.get(&(contract.to_string(), address)).copied().unwrap_or_default(),
+                }),
+                Cw20QueryMsg::Minter {} => respond(&chain.minters.get(contract).map(|minter| MinterResponse {
+                    minter: minter.clone(),
+                    cap: None,
+                })),
+                _ => unsupported(msg),
+            };
+        }
+        match from_binary::<Cw721QueryMsg>(msg) {
+            Ok(Cw721QueryMsg::Tokens { owner, start_after, limit }) => {
+                // cw721-base never hands out more than 30 tokens a page
+                let held = chain.nfts.get(&(contract.to_string(), owner)).copied().unwrap_or_default();
+                let tokens = (0..held)
+                    .map(|token| format!("token{:05}", token))
+                    .filter(|token| start_after.as_ref().is_none_or(|start_after| token > start_after))
+                    .take(limit.unwrap_or(10).min(30) as usize)
+                    .collect();
+                respond(&TokensResponse { tokens })
+            },
+            Err(_) => unsupported(msg),
+        }
+    }
+}
 
-        // it worked, let's query the state
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCount {}).unwrap();
-        let value: CountResponse = from_binary(&res).unwrap();
-        assert_eq!(17, value.count);
+fn env_at(time: u64) -> Env {
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(time);
+    env.block.height = time;
+    env
+}
+
+// one MIN per UST per thousand seconds at tier 0, each later tier a thousand seconds on and paying more
+fn campaign_msg() -> InstantiateMsg {
+    InstantiateMsg {
+        owner: None,
+        treasury_wallet: Addr::unchecked("treasury"),
+        reward_contract: Addr::unchecked("reward_contract"),
+        reward_token: REWARD_TOKEN.to_string(),
+        reward_mode: RewardMode::Mint,
+        ust_deposited: Uint128::zero(),
+        sellback_price: Decimal256::percent(50),
+        anc_market: ANC_MARKET.to_string(),
+        aust_contract: AUST.to_string(),
+        tier0rate: Decimal256::permille(1),
+        tier0time: 0,
+        tier1rate: Decimal256::permille(2),
+        tier1time: 1_000,
+        tier2rate: Decimal256::permille(3),
+        tier2time: 2_000,
+        tier3rate: Decimal256::permille(4),
+        tier3time: 3_000,
+        start_time: Some(START),
+        end_time: None,
+        deposit_cap: None,
+        soft_cap: None,
+        deadline: None,
+        yield_split: None,
     }
+}
 
-    #[test]
-    fn deposit() {
-        let mut deps = mock_dependencies(&coins(2, "token"));
+fn setup_with(msg: InstantiateMsg) -> (Deps, Rc<RefCell<Chain>>) {
+    let chain = Rc::new(RefCell::new(Chain {
+        exchange_rate: Decimal256::one(),
+        balances: HashMap::new(),
+        nfts: HashMap::new(),
+        minters: HashMap::new(),
+        pools: HashMap::new(),
+        broken: vec![],
+    }));
+    let mut deps = OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier: ChainQuerier {
+            base: MockQuerier::new(&[]),
+            chain: chain.clone(),
+        },
+    };
+    instantiate(deps.as_mut(), env_at(START), mock_info(OWNER, &[]), msg).unwrap();
+    (deps, chain)
+}
+
+fn setup() -> (Deps, Rc<RefCell<Chain>>) {
+    setup_with(campaign_msg())
+}
+
+fn run(deps: &mut Deps, time: u64, sender: &str, msg: ExecuteMsg) -> Result<Response, ContractError> {
+    execute(deps.as_mut(), env_at(time), mock_info(sender, &[]), msg)
+}
+
+fn deposit(deps: &mut Deps, time: u64, address: &str, amount: u128) -> Result<Response, ContractError> {
+    let msg = ExecuteMsg::DepositUst {
+        campaign_id: 0,
+        entry_address: address.to_string(),
+        amount: Uint128::from(amount),
+        referrer: None,
+    };
+    execute(deps.as_mut(), env_at(time), mock_info(address, &coins(amount, "uust")), msg)
+}
+
+fn fund_rewards(deps: &mut Deps, amount: u128) {
+    run(deps, START, OWNER, ExecuteMsg::FundRewards { campaign_id: 0, amount: Uint128::from(amount) }).unwrap();
+}
+
+fn claim(deps: &mut Deps, time: u64, address: &str) -> Result<Response, ContractError> {
+    run(deps, time, address, ExecuteMsg::ClaimReward { campaign_id: 0, entry_address: address.to_string() })
+}
 
-        let msg = InstantiateMsg { count: 17 };
-        let info = mock_info("creator", &coins(2, "token"));
-        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+fn entry(deps: &Deps, time: u64, address: &str) -> EntryResponse {
+    let msg = QueryMsg::GetEntry { campaign_id: 0, entry_address: address.to_string() };
+    from_binary(&query(deps.as_ref(), env_at(time), msg).unwrap()).unwrap()
+}
+
+// alice's deposit doubles in value and all of the yield goes to the sellback reserve
+fn setup_reserve() -> (Deps, Rc<RefCell<Chain>>) {
+    let (mut deps, chain) = setup_with(InstantiateMsg {
+        yield_split: Some(vec![YieldShare { recipient: YieldRecipient::SellbackReserve {}, share: Decimal256::one() }]),
+        ..campaign_msg()
+    });
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    chain.borrow_mut().exchange_rate = Decimal256::percent(200);
+    run(&mut deps, START, OWNER, ExecuteMsg::CashoutYield { campaign_id: 0 }).unwrap();
+    (deps, chain)
+}
 
-        // beneficiary can release it
-        let info = mock_info("anyone", &coins(2, "token"));
-        let msg = ExecuteMsg::Increment {};
-        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+// a raise that has to take in `soft_cap` within a thousand seconds
+fn setup_raise(soft_cap: u128) -> (Deps, Rc<RefCell<Chain>>) {
+    setup_with(InstantiateMsg {
+        soft_cap: Some(Uint128::from(soft_cap)),
+        deadline: Some(START + 1_000),
+        ..campaign_msg()
+    })
+}
+
+fn withdraw_all(deps: &mut Deps, time: u64, address: &str) -> Result<Response, ContractError> {
+    let amount = entry(deps, time, address).ust_deposited;
+    run(deps, time, address, ExecuteMsg::WithdrawUst { campaign_id: 0, entry_address: address.to_string(), amount, order: None })
+}
 
-        // should increase counter by 1
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCount {}).unwrap();
-        let value: CountResponse = from_binary(&res).unwrap();
-        assert_eq!(18, value.count);
+fn fetch<T: DeserializeOwned>(deps: &Deps, time: u64, msg: QueryMsg) -> T {
+    from_binary(&query(deps.as_ref(), env_at(time), msg).unwrap()).unwrap()
+}
+
+fn campaign(deps: &Deps) -> CampaignResponse {
+    from_binary(&query(deps.as_ref(), env_at(START), QueryMsg::GetCampaign { campaign_id: 0 }).unwrap()).unwrap()
+}
+
+fn sell_allowance(deps: &Deps, time: u64, address: &str) -> SellAllowanceResponse {
+    let msg = QueryMsg::GetSellAllowance { campaign_id: 0, entry_address: address.to_string() };
+    from_binary(&query(deps.as_ref(), env_at(time), msg).unwrap()).unwrap()
+}
+
+// holding `min_holding` of the partner's tokens doubles the reward
+fn set_partner(deps: &mut Deps, kind: PartnerKind, min_holding: u128) {
+    let msg = ExecuteMsg::SetPartnerBoost {
+        campaign_id: 0,
+        contract: PARTNER.to_string(),
+        kind,
+        brackets: vec![BoostBracket { min_holding: Uint128::from(min_holding), boost: Decimal256::one() }],
+    };
+    run(deps, START, OWNER, msg).unwrap();
+}
+
+fn register_dp_token(deps: &mut Deps, chain: &Rc<RefCell<Chain>>) -> Result<Response, ContractError> {
+    chain.borrow_mut().minters.insert(DP_TOKEN.to_string(), MOCK_CONTRACT_ADDR.to_string());
+    run(deps, START, OWNER, ExecuteMsg::RegisterDpToken { campaign_id: 0, address: DP_TOKEN.to_string() })
+}
+
+// the value of `key` on the response's first event of type `ty`
+fn event_attr(response: &Response, ty: &str, key: &str) -> Option<String> {
+    response.events
+        .iter()
+        .find(|event| event.ty == ty)
+        .and_then(|event| event.attributes.iter().find(|attribute| attribute.key == key))
+        .map(|attribute| attribute.value.clone())
+}
+
+fn attr(response: &Response, key: &str) -> Option<String> {
+    response.attributes.iter().find(|attribute| attribute.key == key).map(|attribute| attribute.value.clone())
+}
+
+#[test]
+fn low_budget_is_shared_pro_rata_whoever_claims_first() {
+    let (mut deps, _) = setup();
+    fund_rewards(&mut deps, 150_000);
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    deposit(&mut deps, START, "bob", 1_000_000).unwrap();
+
+    // both earned 100_000 MIN but the budget only covers three quarters of it
+    let alice = claim(&mut deps, START + 100, "alice").unwrap();
+    let bob = claim(&mut deps, START + 100, "bob").unwrap();
+    assert_eq!(event_attr(&alice, "ipr_claim", "amount").unwrap(), "75000");
+    assert_eq!(event_attr(&bob, "ipr_claim", "amount").unwrap(), "75000");
+}
+
+// alice deposits a million at the start, referred by carol for half of what she earns over `period`
+fn setup_referral(budget: u128, period: u64) -> Deps {
+    let (mut deps, _) = setup();
+    fund_rewards(&mut deps, budget);
+    run(&mut deps, START, OWNER, ExecuteMsg::SetReferralTerms { campaign_id: 0, share: Decimal256::percent(50), period }).unwrap();
+    let msg = ExecuteMsg::DepositUst {
+        campaign_id: 0,
+        entry_address: "alice".to_string(),
+        amount: Uint128::from(1_000_000u128),
+        referrer: Some("carol".to_string()),
+    };
+    execute(deps.as_mut(), env_at(START), mock_info("alice", &coins(1_000_000, "uust")), msg).unwrap();
+    deps
+}
+
+fn referrer(deps: &Deps, address: &str) -> ReferrerResponse {
+    fetch(deps, START, QueryMsg::GetReferrer { campaign_id: 0, address: address.to_string() })
+}
+
+#[test]
+fn referral_bonus_counts_against_the_budget_for_every_entry() {
+    let mut deps = setup_referral(150_000, 10_000);
+    deposit(&mut deps, START, "bob", 1_000_000).unwrap();
+    // alice and bob earned 100_000 MIN each and carol 50_000 on top, the budget covers three fifths
+    assert_eq!(reward_budget(&deps, START + 100).pending_reward, Uint128::from(250_000u128));
+
+    let bob = claim(&mut deps, START + 100, "bob").unwrap();
+    let alice = claim(&mut deps, START + 100, "alice").unwrap();
+    assert_eq!(event_attr(&bob, "ipr_claim", "amount").unwrap(), "60000");
+    assert_eq!(event_attr(&alice, "ipr_claim", "amount").unwrap(), "60000");
+    assert_eq!(event_attr(&alice, "ipr_referral_reward", "amount").unwrap(), "30000");
+    assert_eq!(referrer(&deps, "carol").unclaimed, Uint128::from(30_000u128));
+}
+
+#[test]
+fn referral_bonus_stops_at_the_end_of_the_period() {
+    let mut deps = setup_referral(10_000_000, 50);
+    let referral: ReferralResponse = fetch(&deps, START, QueryMsg::GetReferral { campaign_id: 0, entry_address: "alice".to_string() });
+    let referral = referral.referral.unwrap();
+    assert_eq!((referral.start, referral.end), (START, START + 50));
+
+    // only the first 50 of alice's 100 seconds pay carol a bonus
+    claim(&mut deps, START + 100, "alice").unwrap();
+    assert_eq!(referrer(&deps, "carol").earned, Uint128::from(25_000u128));
+    // the estimate stops counting carol once alice has settled past the end
+    assert_eq!(reward_budget(&deps, START + 200).pending_reward, Uint128::from(100_000u128));
+}
+
+#[test]
+fn update_entries_settles_a_page_at_a_time() {
+    let (mut deps, _) = setup();
+    fund_rewards(&mut deps, 150_000);
+    for address in ["alice", "bob", "carol"].iter() {
+        deposit(&mut deps, START, address, 1_000_000).unwrap();
     }
 
-    #[test]
-    fn withdraw() {
-        let mut deps = mock_dependencies(&coins(2, "token"));
-
-        let msg = InstantiateMsg { count: 17 };
-        let info = mock_info("creator", &coins(2, "token"));
-        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
-
-        // beneficiary can release it
-        let unauth_info = mock_info("anyone", &coins(2, "token"));
-        let msg = ExecuteMsg::Reset { count: 5 };
-        let res = execute(deps.as_mut(), mock_env(), unauth_info, msg);
-        match res {
-            Err(ContractError::Unauthorized {}) => {}
-            _ => panic!("Must return unauthorized error"),
-        }
+    let first = run(&mut deps, START + 100, OWNER, ExecuteMsg::UpdateEntries { campaign_id: 0, start_after: None, limit: Some(2) }).unwrap();
+    assert_eq!(attr(&first, "last_entry_address").unwrap(), "bob");
+    let second = run(&mut deps, START + 100, OWNER, ExecuteMsg::UpdateEntries {
+        campaign_id: 0,
+        start_after: Some("bob".to_string()),
+        limit: Some(2),
+    }).unwrap();
+    assert_eq!(attr(&second, "last_entry_address").unwrap(), "carol");
+
+    // 300_000 earned against a 150_000 budget, every page throttled by the same half
+    for address in ["alice", "bob", "carol"].iter() {
+        assert_eq!(entry(&deps, START + 100, address).claimable_reward, Uint128::from(50_000u128));
+    }
+}
+
+#[test]
+fn update_entries_is_owner_only() {
+    let (mut deps, _) = setup();
+    let err = run(&mut deps, START, "alice", ExecuteMsg::UpdateEntries { campaign_id: 0, start_after: None, limit: None }).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+}
+
+#[test]
+fn queued_sell_total_follows_queue_fill_and_cancel() {
+    let (mut deps, _) = setup_reserve();
+    run(&mut deps, START, OWNER, ExecuteMsg::SetSellLimits { campaign_id: 0, epoch_length: 1_000, global_cap: None, address_cap: Some(Uint128::from(100u128)) }).unwrap();
+    let sell = ExecuteMsg::SellReward {
+        campaign_id: 0,
+        entry_address: "alice".to_string(),
+        amount: Uint128::from(250u128),
+        on_excess: Some(SellExcess::Queue),
+    };
+    run(&mut deps, START, "alice", sell).unwrap();
+    assert_eq!(sell_allowance(&deps, START, "alice").queued, Uint128::from(150u128));
+    assert_eq!(sell_allowance(&deps, START, "bob").queued, Uint128::zero());
+
+    // the next epoch's cap fills another 100 of it
+    run(&mut deps, START + 1_000, OWNER, ExecuteMsg::ProcessSellQueue { campaign_id: 0, start_after: None, limit: None }).unwrap();
+    assert_eq!(sell_allowance(&deps, START + 1_000, "alice").queued, Uint128::from(50u128));
+
+    run(&mut deps, START + 1_000, "alice", ExecuteMsg::CancelQueuedSell { campaign_id: 0, id: 0 }).unwrap();
+    assert_eq!(sell_allowance(&deps, START + 1_000, "alice").queued, Uint128::zero());
+}
+
+#[test]
+fn partner_boost_applies_from_when_it_is_seen() {
+    let (mut deps, chain) = setup();
+    fund_rewards(&mut deps, 10_000_000);
+    set_partner(&mut deps, PartnerKind::Cw20, 1_000);
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+
+    // picking up partner tokens right before a claim doesn't boost the time already spent without them
+    chain.borrow_mut().balances.insert((PARTNER.to_string(), "alice".to_string()), Uint128::from(1_000u128));
+    let first = claim(&mut deps, START + 100, "alice").unwrap();
+    assert_eq!(event_attr(&first, "ipr_claim", "amount").unwrap(), "100000");
+    let second = claim(&mut deps, START + 200, "alice").unwrap();
+    assert_eq!(event_attr(&second, "ipr_claim", "amount").unwrap(), "200000");
+
+    // and selling them before the next claim loses the boost for the whole time since the last one
+    chain.borrow_mut().balances.remove(&(PARTNER.to_string(), "alice".to_string()));
+    let projected = projected(&deps, START + 300, "alice", START + 300);
+    let third = claim(&mut deps, START + 300, "alice").unwrap();
+    assert_eq!(event_attr(&third, "ipr_claim", "amount").unwrap(), "100000");
+    assert_eq!(projected.projected_reward, Uint128::from(100_000u128));
+}
+
+fn set_stake_terms(deps: &mut Deps, time: u64, boost_rate: Decimal256, boost_cap: Decimal256, unstake_cooldown: u64) -> Result<Response, ContractError> {
+    run(deps, time, OWNER, ExecuteMsg::SetStakeTerms { campaign_id: 0, boost_rate, boost_cap, unstake_cooldown })
+}
+
+fn stake(deps: &mut Deps, time: u64, address: &str, amount: u128) -> Result<Response, ContractError> {
+    run(deps, time, address, ExecuteMsg::StakeReward { campaign_id: 0, entry_address: address.to_string(), amount: Uint128::from(amount) })
+}
+
+#[test]
+fn stake_terms_apply_from_the_next_settle() {
+    let (mut deps, _) = setup();
+    fund_rewards(&mut deps, 10_000_000);
+    set_stake_terms(&mut deps, START, Decimal256::one(), Decimal256::one(), 100).unwrap();
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    // a tenth of a MIN staked per UST deposited boosts the reward by a tenth
+    stake(&mut deps, START + 100, "alice", 100_000).unwrap();
+
+    // doubling the rate halfway doesn't reach back over the time alice already accrued at 1.1
+    set_stake_terms(&mut deps, START + 200, Decimal256::percent(200), Decimal256::percent(200), 100).unwrap();
+    assert_eq!(entry(&deps, START + 200, "alice").stake_boost, Decimal256::percent(120));
+    let first = claim(&mut deps, START + 300, "alice").unwrap();
+    assert_eq!(event_attr(&first, "ipr_claim", "amount").unwrap(), "220000");
+    let second = claim(&mut deps, START + 400, "alice").unwrap();
+    assert_eq!(event_attr(&second, "ipr_claim", "amount").unwrap(), "120000");
+}
+
+#[test]
+fn unstaking_drops_the_boost_from_then_on() {
+    let (mut deps, _) = setup();
+    fund_rewards(&mut deps, 10_000_000);
+    set_stake_terms(&mut deps, START, Decimal256::one(), Decimal256::one(), 1_000).unwrap();
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    stake(&mut deps, START + 100, "alice", 100_000).unwrap();
+    run(&mut deps, START + 200, "alice", ExecuteMsg::UnstakeReward { campaign_id: 0, entry_address: "alice".to_string(), amount: Uint128::from(100_000u128) }).unwrap();
+
+    // 110_000 at the boost until the unstake, 100_000 without it after, the unstaked MIN is still cooling down
+    let claimed = claim(&mut deps, START + 300, "alice").unwrap();
+    assert_eq!(event_attr(&claimed, "ipr_claim", "amount").unwrap(), "210000");
+}
+
+#[test]
+fn stake_terms_are_bounded() {
+    let (mut deps, _) = setup();
+    let err = set_stake_terms(&mut deps, START, Decimal256::percent(50), Decimal256::percent(40), 100).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidStakeTerms {}));
+    let err = set_stake_terms(&mut deps, START, Decimal256::one(), Decimal256::percent(1_001), 100).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidStakeTerms {}));
+    let err = set_stake_terms(&mut deps, START, Decimal256::one(), Decimal256::one(), 90 * 86_400 + 1).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidStakeTerms {}));
+    set_stake_terms(&mut deps, START, Decimal256::percent(1_000), Decimal256::percent(1_000), 90 * 86_400).unwrap();
+    let err = run(&mut deps, START, "alice", ExecuteMsg::SetStakeTerms { campaign_id: 0, boost_rate: Decimal256::one(), boost_cap: Decimal256::one(), unstake_cooldown: 0 }).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+}
+
+#[test]
+fn deposit_starts_at_the_boost_already_held() {
+    let (mut deps, chain) = setup();
+    fund_rewards(&mut deps, 10_000_000);
+    set_partner(&mut deps, PartnerKind::Cw20, 1_000);
+    chain.borrow_mut().balances.insert((PARTNER.to_string(), "alice".to_string()), Uint128::from(1_000u128));
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+
+    let claimed = claim(&mut deps, START + 100, "alice").unwrap();
+    assert_eq!(event_attr(&claimed, "ipr_claim", "amount").unwrap(), "200000");
+}
+
+#[test]
+fn cw721_holding_is_counted_across_pages() {
+    let (mut deps, chain) = setup();
+    set_partner(&mut deps, PartnerKind::Cw721, 45);
+    chain.borrow_mut().nfts.insert((PARTNER.to_string(), "alice".to_string()), 50);
+    chain.borrow_mut().nfts.insert((PARTNER.to_string(), "bob".to_string()), 44);
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    deposit(&mut deps, START, "bob", 1_000_000).unwrap();
+
+    assert_eq!(entry(&deps, START, "alice").reward_boost, Decimal256::percent(200));
+    assert_eq!(entry(&deps, START, "bob").reward_boost, Decimal256::one());
+}
+
+#[test]
+fn broken_partner_gives_no_boost() {
+    let (mut deps, chain) = setup();
+    fund_rewards(&mut deps, 10_000_000);
+    set_partner(&mut deps, PartnerKind::Cw20, 1_000);
+    chain.borrow_mut().balances.insert((PARTNER.to_string(), "alice".to_string()), Uint128::from(1_000u128));
+    chain.borrow_mut().broken.push(PARTNER.to_string());
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    assert_eq!(entry(&deps, START, "alice").reward_boost, Decimal256::one());
+
+    // once the partner answers again its boost is seen, and paid from then on
+    chain.borrow_mut().broken.clear();
+    let first = claim(&mut deps, START + 100, "alice").unwrap();
+    assert_eq!(event_attr(&first, "ipr_claim", "amount").unwrap(), "100000");
+    let second = claim(&mut deps, START + 200, "alice").unwrap();
+    assert_eq!(event_attr(&second, "ipr_claim", "amount").unwrap(), "200000");
+}
+
+#[test]
+fn withdraw_settles_without_the_partner_boost() {
+    let (mut deps, chain) = setup();
+    fund_rewards(&mut deps, 10_000_000);
+    set_partner(&mut deps, PartnerKind::Cw20, 1_000);
+    chain.borrow_mut().balances.insert((PARTNER.to_string(), "alice".to_string()), Uint128::from(1_000u128));
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+
+    // a broken partner doesn't hold up the withdrawal, which counts the holdings as gone
+    chain.borrow_mut().broken.push(PARTNER.to_string());
+    let msg = ExecuteMsg::WithdrawUst { campaign_id: 0, entry_address: "alice".to_string(), amount: Uint128::from(500_000u128), order: None };
+    run(&mut deps, START + 100, "alice", msg).unwrap();
+    chain.borrow_mut().broken.clear();
+    assert_eq!(entry(&deps, START + 100, "alice").claimable_reward, Uint128::from(100_000u128));
+}
+
+#[test]
+fn dp_token_is_registered_by_the_owner_before_deposits() {
+    let (mut deps, chain) = setup();
+    let msg = ExecuteMsg::RegisterDpToken { campaign_id: 0, address: DP_TOKEN.to_string() };
+    let err = run(&mut deps, START, "alice", msg).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    let err = register_dp_token(&mut deps, &chain).unwrap_err();
+    assert!(matches!(err, ContractError::DpTokenAfterDeposits {}));
+}
+
+#[test]
+fn dp_token_has_to_be_minted_by_the_campaign() {
+    let (mut deps, chain) = setup();
+    chain.borrow_mut().minters.insert(DP_TOKEN.to_string(), "someone_else".to_string());
+    let msg = ExecuteMsg::RegisterDpToken { campaign_id: 0, address: DP_TOKEN.to_string() };
+    let err = run(&mut deps, START, OWNER, msg).unwrap_err();
+    assert!(matches!(err, ContractError::DpTokenNotMinter {}));
+
+    register_dp_token(&mut deps, &chain).unwrap();
+    let err = register_dp_token(&mut deps, &chain).unwrap_err();
+    assert!(matches!(err, ContractError::DpTokenAlreadyRegistered {}));
+}
+
+#[test]
+fn deposit_mints_receipts_for_the_principal() {
+    let (mut deps, chain) = setup();
+    register_dp_token(&mut deps, &chain).unwrap();
+    let response = deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    let mint = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: DP_TOKEN.to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Mint { recipient: "alice".to_string(), amount: Uint128::from(1_000_000u128) }).unwrap(),
+        funds: vec![],
+    });
+    assert!(response.messages.iter().any(|sub_msg| sub_msg.msg == mint));
+}
+
+#[test]
+fn only_the_dp_token_redeems() {
+    let (mut deps, _) = setup();
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    let redeem = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "alice".to_string(),
+        amount: Uint128::from(1_000_000u128),
+        msg: to_binary(&Cw20HookMsg::Redeem { campaign_id: 0 }).unwrap(),
+    });
+    // without a receipt token registered no cw20 can redeem
+    let err = run(&mut deps, START, DP_TOKEN, redeem.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+    let err = run(&mut deps, START, REWARD_TOKEN, redeem).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+}
+
+#[test]
+fn only_the_dp_token_reports_receipt_transfers() {
+    let (mut deps, chain) = setup();
+    register_dp_token(&mut deps, &chain).unwrap();
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+
+    let transfer = ExecuteMsg::ReceiptTransferred {
+        campaign_id: 0,
+        from: "alice".to_string(),
+        to: "mallory".to_string(),
+        amount: Uint128::from(1_000_000u128),
+    };
+    let err = run(&mut deps, START, "mallory", transfer.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+
+    run(&mut deps, START, DP_TOKEN, transfer).unwrap();
+    assert_eq!(entry(&deps, START, "mallory").ust_deposited, Uint128::from(1_000_000u128));
+    assert_eq!(entry(&deps, START, "alice").ust_deposited, Uint128::zero());
+}
+
+#[test]
+fn dp_token_principal_only_comes_out_through_redeem() {
+    let (mut deps, chain) = setup();
+    register_dp_token(&mut deps, &chain).unwrap();
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+
+    let withdraw = ExecuteMsg::WithdrawUst {
+        campaign_id: 0,
+        entry_address: "alice".to_string(),
+        amount: Uint128::from(400_000u128),
+        order: None,
+    };
+    let err = run(&mut deps, START, "alice", withdraw).unwrap_err();
+    assert!(matches!(err, ContractError::WithdrawWithReceiptToken {}));
+
+    let redeem = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "alice".to_string(),
+        amount: Uint128::from(400_000u128),
+        msg: to_binary(&Cw20HookMsg::Redeem { campaign_id: 0 }).unwrap(),
+    });
+    let err = run(&mut deps, START, "mallory", redeem.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+
+    let response = run(&mut deps, START, DP_TOKEN, redeem).unwrap();
+    let burn = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: DP_TOKEN.to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Burn { amount: Uint128::from(400_000u128) }).unwrap(),
+        funds: vec![],
+    });
+    assert!(response.messages.iter().any(|sub_msg| sub_msg.msg == burn));
+    assert_eq!(entry(&deps, START, "alice").ust_deposited, Uint128::from(600_000u128));
+}
+
+fn transfer_entry(deps: &mut Deps, time: u64, from: &str, to: &str, amount: u128) -> Result<Response, ContractError> {
+    run(deps, time, from, ExecuteMsg::TransferEntry { campaign_id: 0, to: to.to_string(), amount: Uint128::from(amount) })
+}
+
+#[test]
+fn transfer_entry_keeps_the_tier_ages() {
+    let (mut deps, _) = setup();
+    fund_rewards(&mut deps, 10_000_000);
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+
+    let res = transfer_entry(&mut deps, START + 1_500, "alice", "bob", 400_000).unwrap();
+    assert_eq!(event_attr(&res, "ipr_position_transfer", "tranche_times").unwrap(), START.to_string());
+    let bob = entry(&deps, START + 1_500, "bob");
+    assert_eq!(bob.ust_deposited, Uint128::from(400_000u128));
+    assert_eq!(bob.dynamic_reward_log, vec![tranche(400_000, START, 1)]);
+    // what alice earned before the transfer stays with her
+    assert_eq!(bob.claimable_reward, Uint128::zero());
+    assert_eq!(entry(&deps, START + 1_500, "alice").ust_deposited, Uint128::from(600_000u128));
+
+    // bob's part goes on earning at tier 1 straight away
+    let claimed = claim(&mut deps, START + 1_600, "bob").unwrap();
+    assert_eq!(event_attr(&claimed, "ipr_claim", "amount").unwrap(), "80000");
+    assert_eq!(stats(&deps, 0).depositor_count, 2);
+}
+
+#[test]
+fn transfer_entry_moving_everything_hands_over_the_depositor() {
+    let (mut deps, _) = setup();
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    transfer_entry(&mut deps, START + 100, "alice", "bob", 1_000_000).unwrap();
+    assert_eq!(entry(&deps, START + 100, "alice").ust_deposited, Uint128::zero());
+    assert_eq!(stats(&deps, 0).depositor_count, 1);
+    assert_eq!(deposited_at(&deps, Some("bob"), None), Uint128::from(1_000_000u128));
+}
+
+#[test]
+fn transfer_entry_is_checked() {
+    let (mut deps, _) = setup();
+    let err = transfer_entry(&mut deps, START, "alice", "bob", 100).unwrap_err();
+    assert!(matches!(err, ContractError::CannotWithdrawWithoutDeposit {}));
+    deposit(&mut deps, START, "alice", 1_000).unwrap();
+    let err = transfer_entry(&mut deps, START, "alice", "bob", 0).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidZeroAmount {}));
+    let err = transfer_entry(&mut deps, START, "alice", "alice", 100).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidTransferRecipient {}));
+    assert!(transfer_entry(&mut deps, START, "alice", "bob", 1_001).is_err());
+
+    // with receipts the position moves with them instead
+    let (mut deps, chain) = setup();
+    register_dp_token(&mut deps, &chain).unwrap();
+    deposit(&mut deps, START, "alice", 1_000).unwrap();
+    let err = transfer_entry(&mut deps, START, "alice", "bob", 100).unwrap_err();
+    assert!(matches!(err, ContractError::TransferWithReceiptToken {}));
+}
+
+// alice's tranches have reached tiers 2, 1 and 0 when 350 of her 600 is withdrawn
+fn withdraw_in_order(order: DepletionOrder) -> WithdrawResponse {
+    let (mut deps, _) = setup();
+    deposit(&mut deps, START, "alice", 100).unwrap();
+    deposit(&mut deps, START + 1_500, "alice", 200).unwrap();
+    deposit(&mut deps, START + 2_000, "alice", 300).unwrap();
+    let msg = ExecuteMsg::WithdrawUst {
+        campaign_id: 0,
+        entry_address: "alice".to_string(),
+        amount: Uint128::from(350u128),
+        order: Some(order),
+    };
+    let response = run(&mut deps, START + 2_600, "alice", msg).unwrap();
+    from_binary(&response.data.unwrap()).unwrap()
+}
+
+fn tranche(amount: u128, time: u64, reward_tier: u8) -> Reward {
+    Reward { amount: Uint128::from(amount), time, reward_tier }
+}
+
+#[test]
+fn withdraw_oldest_first() {
+    let data = withdraw_in_order(DepletionOrder::OldestFirst);
+    assert_eq!(data.depleted_tranches, vec![tranche(100, START, 2), tranche(200, START + 1_500, 1), tranche(50, START + 2_000, 0)]);
+    assert_eq!(data.dynamic_reward_log, vec![tranche(0, START, 2), tranche(0, START + 1_500, 1), tranche(250, START + 2_000, 0)]);
+}
+
+#[test]
+fn withdraw_newest_first() {
+    let data = withdraw_in_order(DepletionOrder::NewestFirst);
+    assert_eq!(data.depleted_tranches, vec![tranche(50, START + 1_500, 1), tranche(300, START + 2_000, 0)]);
+    assert_eq!(data.dynamic_reward_log, vec![tranche(100, START, 2), tranche(150, START + 1_500, 1), tranche(0, START + 2_000, 0)]);
+}
+
+#[test]
+fn withdraw_lowest_tier_first() {
+    let data = withdraw_in_order(DepletionOrder::LowestTierFirst);
+    assert_eq!(data.depletion_order, DepletionOrder::LowestTierFirst);
+    assert_eq!(data.depleted_tranches, vec![tranche(50, START + 1_500, 1), tranche(300, START + 2_000, 0)]);
+    assert_eq!(data.dynamic_reward_log, vec![tranche(100, START, 2), tranche(150, START + 1_500, 1), tranche(0, START + 2_000, 0)]);
+}
+
+#[test]
+fn lowest_tier_first_takes_the_newest_of_a_tier_first() {
+    let (mut deps, _) = setup();
+    deposit(&mut deps, START, "alice", 100).unwrap();
+    deposit(&mut deps, START + 100, "alice", 100).unwrap();
+    deposit(&mut deps, START + 1_500, "alice", 100).unwrap();
+    let msg = ExecuteMsg::WithdrawUst {
+        campaign_id: 0,
+        entry_address: "alice".to_string(),
+        amount: Uint128::from(150u128),
+        order: Some(DepletionOrder::LowestTierFirst),
+    };
+    // at START + 1_600 the first two tranches are both in tier 1 and the last still in tier 0
+    let response = run(&mut deps, START + 1_600, "alice", msg).unwrap();
+    let data: WithdrawResponse = from_binary(&response.data.unwrap()).unwrap();
+    assert_eq!(data.depleted_tranches, vec![tranche(50, START + 100, 1), tranche(100, START + 1_500, 0)]);
+}
+
+#[test]
+fn campaign_takes_its_wallets_from_the_terms() {
+    let (deps, _) = setup();
+    let campaign = campaign(&deps);
+    assert_eq!(campaign.owner, Addr::unchecked(OWNER));
+    assert_eq!(campaign.treasury_wallet, Addr::unchecked("treasury"));
+    assert_eq!(campaign.reward_contract, Addr::unchecked("reward_contract"));
+}
+
+#[test]
+fn soft_cap_never_counts_seeded_principal() {
+    let mut deps = setup().0;
+    let msg = InstantiateMsg {
+        ust_deposited: Uint128::from(1_000_000u128),
+        soft_cap: Some(Uint128::from(1_000_000u128)),
+        deadline: Some(START + 1_000),
+        ..campaign_msg()
+    };
+    let err = instantiate(deps.as_mut(), env_at(START), mock_info(OWNER, &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::SoftCapWithDeposits {}));
+
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    let criteria = ExecuteMsg::SetSuccessCriteria { campaign_id: 0, soft_cap: Some(Uint128::from(1_000_000u128)), deadline: Some(START + 1_000) };
+    let err = run(&mut deps, START, OWNER, criteria).unwrap_err();
+    assert!(matches!(err, ContractError::SoftCapWithDeposits {}));
+}
+
+#[test]
+fn raise_that_reaches_its_soft_cap_succeeds() {
+    let (mut deps, chain) = setup_raise(1_000_000);
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    chain.borrow_mut().exchange_rate = Decimal256::percent(110);
+    let err = run(&mut deps, START, OWNER, ExecuteMsg::CashoutYield { campaign_id: 0 }).unwrap_err();
+    assert!(matches!(err, ContractError::YieldHeldUntilSuccess {}));
+
+    let err = run(&mut deps, START + 999, "anyone", ExecuteMsg::Finalize { campaign_id: 0 }).unwrap_err();
+    assert!(matches!(err, ContractError::DeadlineNotReached { .. }));
+    run(&mut deps, START + 1_000, "anyone", ExecuteMsg::Finalize { campaign_id: 0 }).unwrap();
+    assert_eq!(campaign(&deps).status, CampaignStatus::Succeeded);
+    let err = run(&mut deps, START + 1_000, "anyone", ExecuteMsg::Finalize { campaign_id: 0 }).unwrap_err();
+    assert!(matches!(err, ContractError::CampaignFinalized {}));
+
+    let cashout = run(&mut deps, START + 1_000, OWNER, ExecuteMsg::CashoutYield { campaign_id: 0 }).unwrap();
+    assert_eq!(event_attr(&cashout, "ipr_cashout_yield", "amount").unwrap(), "100000");
+}
+
+#[test]
+fn failed_raise_refunds_principal_with_its_share_of_the_yield() {
+    let (mut deps, chain) = setup_raise(3_000_000);
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    deposit(&mut deps, START, "bob", 500_000).unwrap();
+    chain.borrow_mut().exchange_rate = Decimal256::percent(120);
+    let err = deposit(&mut deps, START + 1_000, "carol", 1_500_000).unwrap_err();
+    assert!(matches!(err, ContractError::CampaignNotOpen {}));
+
+    run(&mut deps, START + 1_000, "anyone", ExecuteMsg::Finalize { campaign_id: 0 }).unwrap();
+    assert_eq!(campaign(&deps).status, CampaignStatus::Failed);
+    let err = claim(&mut deps, START + 1_000, "alice").unwrap_err();
+    assert!(matches!(err, ContractError::RewardsVoided {}));
+
+    let alice = withdraw_all(&mut deps, START + 1_000, "alice").unwrap();
+    // the first refund rounds down, the last one takes what is left
+    assert_eq!(event_attr(&alice, "ipr_withdraw", "ust_amount").unwrap(), "1199998");
+    let bob = withdraw_all(&mut deps, START + 1_000, "bob").unwrap();
+    assert_eq!(event_attr(&bob, "ipr_withdraw", "ust_amount").unwrap(), "600001");
+}
+
+fn sell(deps: &mut Deps, time: u64, address: &str, amount: u128) -> Result<Response, ContractError> {
+    let msg = ExecuteMsg::SellReward {
+        campaign_id: 0,
+        entry_address: address.to_string(),
+        amount: Uint128::from(amount),
+        on_excess: None,
+    };
+    run(deps, time, address, msg)
+}
+
+#[test]
+fn raise_holds_rewards_until_it_succeeds() {
+    let (mut deps, _) = setup_raise(1_000_000);
+    fund_rewards(&mut deps, 10_000_000);
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+
+    let err = claim(&mut deps, START + 100, "alice").unwrap_err();
+    assert!(matches!(err, ContractError::RewardsHeldUntilSuccess {}));
+    let err = sell(&mut deps, START + 100, "alice", 100).unwrap_err();
+    assert!(matches!(err, ContractError::RewardsHeldUntilSuccess {}));
+    let err = run(&mut deps, START + 100, OWNER, ExecuteMsg::ProcessSellQueue { campaign_id: 0, start_after: None, limit: None }).unwrap_err();
+    assert!(matches!(err, ContractError::RewardsHeldUntilSuccess {}));
+    let simulate = QueryMsg::SimulateClaim { campaign_id: 0, entry_address: "alice".to_string() };
+    assert!(query(deps.as_ref(), env_at(START + 100), simulate).is_err());
+
+    // what accrued while the raise was running is paid out once it succeeds
+    run(&mut deps, START + 1_000, "anyone", ExecuteMsg::Finalize { campaign_id: 0 }).unwrap();
+    let claimed = claim(&mut deps, START + 1_000, "alice").unwrap();
+    assert_eq!(event_attr(&claimed, "ipr_claim", "amount").unwrap(), "1000000");
+}
+
+#[test]
+fn failed_raise_voids_sells() {
+    let (mut deps, _) = setup_raise(1_000_000);
+    run(&mut deps, START + 1_000, "anyone", ExecuteMsg::Finalize { campaign_id: 0 }).unwrap();
+    let err = sell(&mut deps, START + 1_000, "alice", 100).unwrap_err();
+    assert!(matches!(err, ContractError::RewardsVoided {}));
+    let err = run(&mut deps, START + 1_000, OWNER, ExecuteMsg::ProcessSellQueue { campaign_id: 0, start_after: None, limit: None }).unwrap_err();
+    assert!(matches!(err, ContractError::RewardsVoided {}));
+}
+
+#[test]
+fn split_yield_leaves_the_rounding_dust_to_the_last_share() {
+    let shares = vec![
+        YieldShare { recipient: YieldRecipient::Treasury {}, share: Decimal256::percent(33) },
+        YieldShare { recipient: YieldRecipient::SellbackReserve {}, share: Decimal256::percent(33) },
+        YieldShare { recipient: YieldRecipient::Burn {}, share: Decimal256::percent(34) },
+    ];
+    let payouts = split::split_yield(&shares, Uint128::from(1_001u128)).unwrap();
+    assert_eq!(payouts, vec![
+        (YieldRecipient::Treasury {}, Uint128::from(330u128)),
+        (YieldRecipient::SellbackReserve {}, Uint128::from(330u128)),
+        (YieldRecipient::Burn {}, Uint128::from(341u128)),
+    ]);
+}
+
+#[test]
+fn cashout_event_names_each_destination() {
+    let (mut deps, chain) = setup_with(InstantiateMsg {
+        yield_split: Some(vec![
+            YieldShare { recipient: YieldRecipient::Treasury {}, share: Decimal256::percent(60) },
+            YieldShare { recipient: YieldRecipient::FeeCollector { address: Addr::unchecked("fee_collector") }, share: Decimal256::percent(40) },
+        ]),
+        ..campaign_msg()
+    });
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    chain.borrow_mut().exchange_rate = Decimal256::percent(110);
+    let cashout = run(&mut deps, START, OWNER, ExecuteMsg::CashoutYield { campaign_id: 0 }).unwrap();
+
+    assert_eq!(event_attr(&cashout, "ipr_cashout_yield", "recipient"), None);
+    assert_eq!(event_attr(&cashout, "ipr_cashout_yield", "treasury_wallet").unwrap(), "treasury");
+    assert_eq!(event_attr(&cashout, "ipr_cashout_yield", "treasury_amount").unwrap(), "60000");
+    assert_eq!(event_attr(&cashout, "ipr_cashout_yield", "fee_collector").unwrap(), "fee_collector");
+    assert_eq!(event_attr(&cashout, "ipr_cashout_yield", "fee_amount").unwrap(), "40000");
+}
+
+#[test]
+fn simulate_deposit_mints_at_the_exchange_rate() {
+    let (mut deps, chain) = setup();
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    chain.borrow_mut().exchange_rate = Decimal256::percent(125);
 
-        // only the original creator can reset the counter
-        let auth_info = mock_info("creator", &coins(2, "token"));
-        let msg = ExecuteMsg::Reset { count: 5 };
-        let _res = execute(deps.as_mut(), mock_env(), auth_info, msg).unwrap();
+    let msg = QueryMsg::SimulateDeposit { campaign_id: 0, entry_address: "alice".to_string(), amount: Uint128::from(500_000u128) };
+    let simulated: SimulateDepositResponse = fetch(&deps, START + 1_500, msg);
+    assert_eq!(simulated.aust_minted, Uint256::from(400_000u64));
+    assert_eq!(simulated.net_amount, Uint128::from(500_000u128));
+    deposit(&mut deps, START + 1_500, "alice", 500_000).unwrap();
+    assert_eq!(simulated.dynamic_reward_log, entry(&deps, START + 1_500, "alice").dynamic_reward_log);
+}
+
+#[test]
+fn simulate_withdraw_matches_the_withdrawal() {
+    let (mut deps, _) = setup();
+    deposit(&mut deps, START, "alice", 100).unwrap();
+    deposit(&mut deps, START + 1_500, "alice", 200).unwrap();
+
+    let msg = QueryMsg::SimulateWithdraw { campaign_id: 0, entry_address: "alice".to_string(), amount: Uint128::from(150u128), order: Some(DepletionOrder::NewestFirst) };
+    let simulated: SimulateWithdrawResponse = fetch(&deps, START + 2_000, msg);
+    let msg = ExecuteMsg::WithdrawUst { campaign_id: 0, entry_address: "alice".to_string(), amount: Uint128::from(150u128), order: Some(DepletionOrder::NewestFirst) };
+    let response = run(&mut deps, START + 2_000, "alice", msg).unwrap();
+    let data: WithdrawResponse = from_binary(&response.data.unwrap()).unwrap();
+    assert_eq!(simulated.depleted_tranches, data.depleted_tranches);
+    assert_eq!(simulated.dynamic_reward_log, data.dynamic_reward_log);
+    assert_eq!(simulated.aust_burned, Uint256::from(150u64));
+}
+
+fn simulate_sell(deps: &Deps, time: u64, address: &str, amount: u128) -> SimulateSellResponse {
+    let msg = QueryMsg::SimulateSell { campaign_id: 0, entry_address: address.to_string(), amount: Uint128::from(amount) };
+    fetch(deps, time, msg)
+}
+
+#[test]
+fn simulate_sell_fills_within_the_caps() {
+    let (mut deps, _) = setup_reserve();
+    run(&mut deps, START, OWNER, ExecuteMsg::SetSellLimits { campaign_id: 0, epoch_length: 1_000, global_cap: None, address_cap: Some(Uint128::from(100u128)) }).unwrap();
+    sell(&mut deps, START, "alice", 60).unwrap();
+
+    let simulated = simulate_sell(&deps, START, "alice", 250);
+    assert_eq!(simulated.fill_amount, Uint128::from(40u128));
+    assert_eq!(simulated.excess_amount, Uint128::from(210u128));
+    assert_eq!(simulated.ust_amount, Uint128::from(20u128));
+    // bob hasn't sold anything this epoch
+    assert_eq!(simulate_sell(&deps, START, "bob", 250).fill_amount, Uint128::from(100u128));
+}
+
+#[test]
+fn simulate_sell_fills_what_the_reserve_pays_for() {
+    let (deps, _) = setup_reserve();
+    // a reserve of 1_000_000 uust buys 2_000_000 MIN at half a UST each
+    let simulated = simulate_sell(&deps, START, "alice", 3_000_000);
+    assert_eq!(simulated.fill_amount, Uint128::from(2_000_000u128));
+    assert_eq!(simulated.excess_amount, Uint128::from(1_000_000u128));
+    assert_eq!(simulated.ust_amount, Uint128::from(1_000_000u128));
+}
+
+#[test]
+fn sell_queues_what_the_reserve_cannot_pay_for() {
+    let (mut deps, _) = setup_reserve();
+    let err = sell(&mut deps, START, "alice", 3_000_000).unwrap_err();
+    assert!(matches!(err, ContractError::InsufficientSellbackReserve { .. }));
+
+    let msg = ExecuteMsg::SellReward {
+        campaign_id: 0,
+        entry_address: "alice".to_string(),
+        amount: Uint128::from(3_000_000u128),
+        on_excess: Some(SellExcess::Queue),
+    };
+    let res = run(&mut deps, START, "alice", msg).unwrap();
+    assert_eq!(event_attr(&res, "ipr_sell", "amount").unwrap(), "2000000");
+    assert_eq!(event_attr(&res, "ipr_sell_queued", "amount").unwrap(), "1000000");
+    assert_eq!(sellback_reserve(&deps).reserve, Uint128::zero());
+}
+
+fn queue_sell(deps: &mut Deps, time: u64, address: &str, amount: u128) -> Result<Response, ContractError> {
+    let msg = ExecuteMsg::SellReward {
+        campaign_id: 0,
+        entry_address: address.to_string(),
+        amount: Uint128::from(amount),
+        on_excess: Some(SellExcess::Queue),
+    };
+    run(deps, time, address, msg)
+}
+
+#[test]
+fn sell_queue_pages_past_capped_addresses() {
+    let (mut deps, _) = setup_reserve();
+    run(&mut deps, START, OWNER, ExecuteMsg::SetSellLimits { campaign_id: 0, epoch_length: 1_000, global_cap: None, address_cap: Some(Uint128::from(100u128)) }).unwrap();
+    queue_sell(&mut deps, START, "alice", 250).unwrap();
+    queue_sell(&mut deps, START, "bob", 250).unwrap();
+    // alice spends the next epoch's cap herself, her queued sell at the head can't fill
+    sell(&mut deps, START + 1_000, "alice", 100).unwrap();
+
+    let res = run(&mut deps, START + 1_000, OWNER, ExecuteMsg::ProcessSellQueue { campaign_id: 0, start_after: None, limit: Some(1) }).unwrap();
+    assert_eq!(event_attr(&res, "ipr_sell", "entry_address").unwrap(), "bob");
+    assert_eq!(event_attr(&res, "ipr_sell", "queue_id").unwrap(), "1");
+    assert_eq!(attr(&res, "last_queue_id").unwrap(), "1");
+    assert_eq!(sell_allowance(&deps, START + 1_000, "alice").queued, Uint128::from(150u128));
+    assert_eq!(sell_allowance(&deps, START + 1_000, "bob").queued, Uint128::from(50u128));
+
+    // starting after alice's sell only reaches bob's
+    let res = run(&mut deps, START + 2_000, OWNER, ExecuteMsg::ProcessSellQueue { campaign_id: 0, start_after: Some(0), limit: None }).unwrap();
+    assert_eq!(res.events.iter().filter(|event| event.ty == "ipr_sell").count(), 1);
+    assert_eq!(sell_allowance(&deps, START + 2_000, "alice").queued, Uint128::from(150u128));
+    assert_eq!(sell_allowance(&deps, START + 2_000, "bob").queued_sells, 0);
+
+    // oldest first once the cursor is dropped
+    let res = run(&mut deps, START + 2_000, OWNER, ExecuteMsg::ProcessSellQueue { campaign_id: 0, start_after: None, limit: None }).unwrap();
+    assert_eq!(event_attr(&res, "ipr_sell", "queue_id").unwrap(), "0");
+    assert_eq!(sell_allowance(&deps, START + 2_000, "alice").queued, Uint128::from(50u128));
+}
+
+#[test]
+fn queued_sells_are_capped_per_address() {
+    let (mut deps, _) = setup_reserve();
+    run(&mut deps, START, OWNER, ExecuteMsg::SetSellLimits { campaign_id: 0, epoch_length: 1_000, global_cap: None, address_cap: Some(Uint128::from(100u128)) }).unwrap();
+    sell(&mut deps, START, "alice", 100).unwrap();
+    for _ in 0..5 {
+        queue_sell(&mut deps, START, "alice", 10).unwrap();
+    }
+    assert_eq!(sell_allowance(&deps, START, "alice").queued_sells, 5);
+    let err = queue_sell(&mut deps, START, "alice", 10).unwrap_err();
+    assert!(matches!(err, ContractError::TooManyQueuedSells { max: 5 }));
+    // other addresses still queue, and a cancelled sell frees a slot
+    queue_sell(&mut deps, START, "bob", 250).unwrap();
+    run(&mut deps, START, "alice", ExecuteMsg::CancelQueuedSell { campaign_id: 0, id: 0 }).unwrap();
+    queue_sell(&mut deps, START, "alice", 10).unwrap();
+}
+
+#[test]
+fn simulate_sell_fails_while_rewards_are_held() {
+    let (deps, _) = setup_raise(1_000_000);
+    let msg = QueryMsg::SimulateSell { campaign_id: 0, entry_address: "alice".to_string(), amount: Uint128::from(100u128) };
+    assert!(query(deps.as_ref(), env_at(START), msg).is_err());
+}
+
+#[test]
+fn simulate_claim_matches_the_claim() {
+    let (mut deps, _) = setup();
+    fund_rewards(&mut deps, 10_000_000);
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+
+    let simulated: SimulateClaimResponse = fetch(&deps, START + 1_500, QueryMsg::SimulateClaim { campaign_id: 0, entry_address: "alice".to_string() });
+    let claimed = claim(&mut deps, START + 1_500, "alice").unwrap();
+    assert_eq!(event_attr(&claimed, "ipr_claim", "amount").unwrap(), simulated.claim_amount.to_string());
+    assert_eq!(simulated.dynamic_reward_log, vec![tranche(1_000_000, START, 1)]);
+}
+
+fn projected(deps: &Deps, now: u64, address: &str, time: u64) -> ProjectedRewardsResponse {
+    fetch(deps, now, QueryMsg::GetProjectedRewards { campaign_id: 0, entry_address: address.to_string(), time })
+}
+
+#[test]
+fn averaged_rate_weights_the_tranches_by_principal() {
+    let (mut deps, _) = setup();
+    fund_rewards(&mut deps, 10_000_000);
+    deposit(&mut deps, START, "alice", 1_000).unwrap();
+    assert_eq!(entry(&deps, START, "alice").averaged_reward_rate, Decimal256::permille(1));
+
+    // the first tranche has reached tier 1 when the second comes in at tier 0
+    deposit(&mut deps, START + 1_000, "alice", 3_000).unwrap();
+    assert_eq!(entry(&deps, START + 1_000, "alice").averaged_reward_rate, Decimal256::from_ratio(5u64, 4_000u64));
+    // every settle moves it up with the tiers
+    claim(&mut deps, START + 2_000, "alice").unwrap();
+    assert_eq!(entry(&deps, START + 2_000, "alice").averaged_reward_rate, Decimal256::from_ratio(9u64, 4_000u64));
+    // and an empty entry has no rate
+    withdraw_all(&mut deps, START + 2_000, "alice").unwrap();
+    assert_eq!(entry(&deps, START + 2_000, "alice").averaged_reward_rate, Decimal256::zero());
+}
+
+#[test]
+fn projection_reports_the_averaged_rate_at_its_time() {
+    let (mut deps, _) = setup();
+    deposit(&mut deps, START, "alice", 1_000).unwrap();
+    assert_eq!(projected(&deps, START, "alice", START + 3_000).averaged_reward_rate, Decimal256::permille(4));
+}
+
+#[test]
+fn lifetime_rate_is_what_the_entry_earned_per_ust_second() {
+    let (mut deps, _) = setup();
+    fund_rewards(&mut deps, 150_000);
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    deposit(&mut deps, START, "bob", 1_000_000).unwrap();
+    claim(&mut deps, START + 100, "alice").unwrap();
+    // throttled to three quarters of the tier 0 rate
+    assert_eq!(entry(&deps, START + 100, "alice").lifetime_reward_rate, Decimal256::from_ratio(3u64, 4_000u64));
+    assert_eq!(entry(&deps, START + 100, "bob").lifetime_reward_rate, Decimal256::zero());
+}
+
+#[test]
+fn projection_includes_the_partner_boost() {
+    let (mut deps, chain) = setup();
+    fund_rewards(&mut deps, 10_000_000);
+    set_partner(&mut deps, PartnerKind::Cw20, 1_000);
+    chain.borrow_mut().balances.insert((PARTNER.to_string(), "alice".to_string()), Uint128::from(1_000u128));
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+
+    let projection = projected(&deps, START, "alice", START + 100);
+    assert_eq!(projection.projected_reward, Uint128::from(200_000u128));
+    assert_eq!(projection.tranches[0].projected_reward, Uint128::from(200_000u128));
+    let claimed = claim(&mut deps, START + 100, "alice").unwrap();
+    assert_eq!(event_attr(&claimed, "ipr_claim", "amount").unwrap(), "200000");
+}
+
+#[test]
+fn projection_is_throttled_by_the_budget() {
+    let (mut deps, _) = setup();
+    fund_rewards(&mut deps, 150_000);
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    deposit(&mut deps, START, "bob", 1_000_000).unwrap();
+
+    // 200_000 owed by then against a 150_000 budget
+    assert_eq!(projected(&deps, START, "alice", START + 100).projected_reward, Uint128::from(75_000u128));
+    let claimed = claim(&mut deps, START + 100, "alice").unwrap();
+    assert_eq!(event_attr(&claimed, "ipr_claim", "amount").unwrap(), "75000");
+}
+
+#[test]
+fn failed_raise_projects_and_simulates_nothing() {
+    let (mut deps, _) = setup_raise(1_000_000);
+    fund_rewards(&mut deps, 10_000_000);
+    deposit(&mut deps, START, "alice", 500_000).unwrap();
+    run(&mut deps, START + 1_000, "anyone", ExecuteMsg::Finalize { campaign_id: 0 }).unwrap();
+
+    let projection = projected(&deps, START + 1_000, "alice", START + 5_000);
+    assert_eq!(projection.projected_reward, Uint128::zero());
+    assert_eq!(projection.claimable_reward, Uint128::zero());
+    let simulated: SimulateClaimResponse = fetch(&deps, START + 1_000, QueryMsg::SimulateClaim { campaign_id: 0, entry_address: "alice".to_string() });
+    assert_eq!(simulated.claim_amount, Uint128::zero());
+}
+
+fn stats(deps: &Deps, campaign_id: u64) -> StatsResponse {
+    fetch(deps, START, QueryMsg::GetStats { campaign_id })
+}
+
+#[test]
+fn stats_value_the_aust_the_contract_holds() {
+    let (mut deps, chain) = setup();
+    deposit(&mut deps, START, "alice", 600_000).unwrap();
+    deposit(&mut deps, START, "bob", 400_000).unwrap();
+    // anchor minted a little less than the deposits were estimated at
+    chain.borrow_mut().balances.insert((AUST.to_string(), MOCK_CONTRACT_ADDR.to_string()), Uint128::from(900_000u128));
+    chain.borrow_mut().exchange_rate = Decimal256::percent(120);
+
+    let stats = stats(&deps, 0);
+    assert_eq!(stats.aust_balance, Uint128::from(900_000u128));
+    assert_eq!(stats.total_value_locked, Uint256::from(1_080_000u64));
+    assert_eq!(stats.unrealized_yield, Uint256::from(80_000u64));
+    assert_eq!(stats.ust_deposited, Uint128::from(1_000_000u128));
+    assert_eq!(stats.depositor_count, 2);
+}
+
+#[test]
+fn stats_share_the_aust_between_campaigns() {
+    let (mut deps, chain) = setup();
+    run(&mut deps, START, OWNER, ExecuteMsg::CreateCampaign(Box::new(campaign_msg()))).unwrap();
+    deposit(&mut deps, START, "alice", 750_000).unwrap();
+    let msg = ExecuteMsg::DepositUst { campaign_id: 1, entry_address: "bob".to_string(), amount: Uint128::from(250_000u128), referrer: None };
+    execute(deps.as_mut(), env_at(START), mock_info("bob", &coins(250_000, "uust")), msg).unwrap();
+    chain.borrow_mut().balances.insert((AUST.to_string(), MOCK_CONTRACT_ADDR.to_string()), Uint128::from(800_000u128));
+
+    assert_eq!(stats(&deps, 0).aust_balance, Uint128::from(600_000u128));
+    assert_eq!(stats(&deps, 1).aust_balance, Uint128::from(200_000u128));
+}
+
+#[test]
+fn depositor_count_follows_entries_in_and_out() {
+    let (mut deps, _) = setup();
+    deposit(&mut deps, START, "alice", 100).unwrap();
+    deposit(&mut deps, START, "alice", 100).unwrap();
+    deposit(&mut deps, START, "bob", 100).unwrap();
+    assert_eq!(stats(&deps, 0).depositor_count, 2);
+
+    withdraw_all(&mut deps, START + 10, "alice").unwrap();
+    assert_eq!(stats(&deps, 0).depositor_count, 1);
+    assert_eq!(stats(&deps, 0).ust_deposited, Uint128::from(100u128));
+    deposit(&mut deps, START + 20, "alice", 100).unwrap();
+    assert_eq!(stats(&deps, 0).depositor_count, 2);
+}
+
+fn deposited_at(deps: &Deps, address: Option<&str>, height: Option<u64>) -> Uint128 {
+    let msg = match address {
+        Some(address) => QueryMsg::GetDeposited { campaign_id: 0, entry_address: address.to_string(), height },
+        None => QueryMsg::GetTotalDeposited { campaign_id: 0, height },
+    };
+    fetch::<DepositedResponse>(deps, START + 100, msg).ust_deposited
+}
+
+#[test]
+fn deposits_are_read_as_they_stood_at_a_height() {
+    let (mut deps, _) = setup();
+    deposit(&mut deps, START + 10, "alice", 100).unwrap();
+    deposit(&mut deps, START + 20, "alice", 50).unwrap();
+    deposit(&mut deps, START + 20, "bob", 200).unwrap();
+    withdraw_all(&mut deps, START + 30, "alice").unwrap();
+
+    // a height reads the value from the start of that block
+    assert_eq!(deposited_at(&deps, Some("alice"), Some(START + 10)), Uint128::zero());
+    assert_eq!(deposited_at(&deps, Some("alice"), Some(START + 11)), Uint128::from(100u128));
+    assert_eq!(deposited_at(&deps, Some("alice"), Some(START + 21)), Uint128::from(150u128));
+    assert_eq!(deposited_at(&deps, Some("alice"), Some(START + 31)), Uint128::zero());
+    assert_eq!(deposited_at(&deps, Some("alice"), None), Uint128::zero());
+    assert_eq!(deposited_at(&deps, Some("bob"), Some(START + 15)), Uint128::zero());
+
+    assert_eq!(deposited_at(&deps, None, Some(START + 11)), Uint128::from(100u128));
+    assert_eq!(deposited_at(&deps, None, Some(START + 21)), Uint128::from(350u128));
+    assert_eq!(deposited_at(&deps, None, None), Uint128::from(200u128));
+}
+
+fn top_depositors(deps: &Deps, time: u64, by: DepositorRanking) -> TopDepositorsResponse {
+    fetch(deps, time, QueryMsg::TopDepositors { campaign_id: 0, limit: None, by })
+}
+
+fn update_entries(deps: &mut Deps, time: u64) {
+    run(deps, time, OWNER, ExecuteMsg::UpdateEntries { campaign_id: 0, start_after: None, limit: None }).unwrap();
+}
+
+#[test]
+fn top_depositors_rank_by_current_deposit() {
+    let (mut deps, _) = setup();
+    deposit(&mut deps, START, "alice", 100).unwrap();
+    deposit(&mut deps, START, "bob", 300).unwrap();
+    deposit(&mut deps, START, "carol", 200).unwrap();
+    withdraw_all(&mut deps, START, "carol").unwrap();
+
+    let ranking = top_depositors(&deps, START, DepositorRanking::UstDeposited);
+    let ranked: Vec<(String, u128)> = ranking.depositors.iter().map(|rank| (rank.address.to_string(), rank.ust_deposited.u128())).collect();
+    // carol has nothing left in and drops out of the ranking
+    assert_eq!(ranked, vec![("bob".to_string(), 300), ("alice".to_string(), 100)]);
+}
+
+#[test]
+fn time_weighted_ranking_is_as_of_each_entry_settle() {
+    let (mut deps, _) = setup();
+    deposit(&mut deps, START, "alice", 100).unwrap();
+    deposit(&mut deps, START + 50, "bob", 100).unwrap();
+    update_entries(&mut deps, START + 100);
+
+    let ranking = top_depositors(&deps, START + 100, DepositorRanking::TimeWeightedDeposit);
+    assert_eq!(ranking.depositors[0].address, Addr::unchecked("alice"));
+    assert_eq!(ranking.depositors[0].time_weighted_deposit, Uint128::from(10_000u128));
+    assert_eq!(ranking.depositors[1].time_weighted_deposit, Uint128::from(5_000u128));
+
+    // bob settles on his deposit while alice's value is left where her last settle put it
+    deposit(&mut deps, START + 200, "bob", 1_000).unwrap();
+    let ranking = top_depositors(&deps, START + 200, DepositorRanking::TimeWeightedDeposit);
+    assert_eq!(ranking.depositors[0].address, Addr::unchecked("bob"));
+    assert_eq!(ranking.depositors[0].time_weighted_deposit, Uint128::from(15_000u128));
+    assert_eq!(ranking.depositors[1].settled_time, START + 100);
+    assert_eq!(ranking.depositors[1].current_time_weighted_deposit, Uint128::from(20_000u128));
+
+    update_entries(&mut deps, START + 200);
+    let ranking = top_depositors(&deps, START + 200, DepositorRanking::TimeWeightedDeposit);
+    assert_eq!(ranking.depositors[0].address, Addr::unchecked("alice"));
+    assert_eq!(ranking.depositors[0].time_weighted_deposit, Uint128::from(20_000u128));
+}
+
+#[test]
+fn deposit_reports_its_tranche_and_sends_the_ust_to_anchor() {
+    let (mut deps, _) = setup();
+    deposit(&mut deps, START, "alice", 100).unwrap();
+    let response = deposit(&mut deps, START + 10, "alice", 50).unwrap();
+
+    assert_eq!(event_attr(&response, "ipr_deposit", "tranche_id").unwrap(), "1");
+    assert_eq!(event_attr(&response, "ipr_deposit", "amount").unwrap(), "50");
+    assert_eq!(event_attr(&response, "ipr_deposit", "ust_deposited").unwrap(), "150");
+    assert!(response.messages.iter().any(|sub_msg| matches!(&sub_msg.msg, CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, .. }) if contract_addr == ANC_MARKET)));
+}
+
+#[test]
+fn withdraw_reports_the_tranches_it_drew_from() {
+    let (mut deps, _) = setup();
+    deposit(&mut deps, START, "alice", 100).unwrap();
+    deposit(&mut deps, START + 10, "alice", 100).unwrap();
+    let msg = ExecuteMsg::WithdrawUst { campaign_id: 0, entry_address: "alice".to_string(), amount: Uint128::from(150u128), order: Some(DepletionOrder::OldestFirst) };
+    let response = run(&mut deps, START + 20, "alice", msg).unwrap();
+
+    assert_eq!(event_attr(&response, "ipr_withdraw", "tranche_ids").unwrap(), "0,1");
+    assert_eq!(event_attr(&response, "ipr_withdraw", "ust_amount").unwrap(), "150");
+    assert_eq!(event_attr(&response, "ipr_withdraw", "ust_deposited").unwrap(), "50");
+    assert!(response.messages.iter().any(|sub_msg| matches!(&sub_msg.msg, CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, .. }) if contract_addr == AUST)));
+}
+
+#[test]
+fn settling_reports_each_tier_promotion() {
+    let (mut deps, _) = setup();
+    deposit(&mut deps, START, "alice", 100).unwrap();
+    let response = deposit(&mut deps, START + 2_500, "alice", 100).unwrap();
+
+    let promotions: Vec<(String, String)> = response.events
+        .iter()
+        .filter(|event| event.ty == "ipr_tier_promoted")
+        .map(|event| {
+            let value = |key: &str| event.attributes.iter().find(|attribute| attribute.key == key).unwrap().value.clone();
+            (value("old_tier"), value("new_tier"))
+        })
+        .collect();
+    assert_eq!(promotions, vec![("0".to_string(), "2".to_string())]);
+    assert_eq!(event_attr(&response, "ipr_tier_promoted", "tranche_id").unwrap(), "0");
+}
+
+// the cw20 messages the response sends to the reward token
+fn reward_token_msgs(response: &Response) -> Vec<Cw20ExecuteMsg> {
+    response.messages
+        .iter()
+        .filter_map(|sub_msg| match &sub_msg.msg {
+            CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, .. }) if contract_addr == REWARD_TOKEN => Some(from_binary(msg).unwrap()),
+            _ => None,
+        })
+        .collect()
+}
 
-        // should now be 5
-        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetCount {}).unwrap();
-        let value: CountResponse = from_binary(&res).unwrap();
-        assert_eq!(5, value.count);
+#[test]
+fn minted_rewards_are_minted_to_the_claimant() {
+    let (mut deps, _) = setup();
+    fund_rewards(&mut deps, 10_000_000);
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    let claimed = claim(&mut deps, START + 100, "alice").unwrap();
+
+    assert_eq!(reward_token_msgs(&claimed), vec![Cw20ExecuteMsg::Mint { recipient: "alice".to_string(), amount: Uint128::from(100_000u128) }]);
+    assert_eq!(event_attr(&claimed, "ipr_claim", "reward_mode").unwrap(), "mint");
+}
+
+#[test]
+fn transferred_rewards_are_pulled_in_and_paid_out_of_the_contract() {
+    let (mut deps, _) = setup_with(InstantiateMsg { reward_mode: RewardMode::Transfer, ..campaign_msg() });
+    let funded = run(&mut deps, START, OWNER, ExecuteMsg::FundRewards { campaign_id: 0, amount: Uint128::from(10_000_000u128) }).unwrap();
+    assert_eq!(reward_token_msgs(&funded), vec![Cw20ExecuteMsg::TransferFrom {
+        owner: OWNER.to_string(),
+        recipient: MOCK_CONTRACT_ADDR.to_string(),
+        amount: Uint128::from(10_000_000u128),
+    }]);
+
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    let claimed = claim(&mut deps, START + 100, "alice").unwrap();
+    assert_eq!(reward_token_msgs(&claimed), vec![Cw20ExecuteMsg::Transfer { recipient: "alice".to_string(), amount: Uint128::from(100_000u128) }]);
+    assert_eq!(event_attr(&claimed, "ipr_claim", "reward_mode").unwrap(), "transfer");
+}
+
+#[test]
+fn minted_rewards_are_only_capped_not_pulled_in() {
+    let (mut deps, _) = setup();
+    let funded = run(&mut deps, START, OWNER, ExecuteMsg::FundRewards { campaign_id: 0, amount: Uint128::from(1_000u128) }).unwrap();
+    assert!(reward_token_msgs(&funded).is_empty());
+    assert_eq!(event_attr(&funded, "ipr_fund_rewards", "reward_budget").unwrap(), "1000");
+}
+
+fn confirm_claim(deps: &mut Deps, time: u64) -> Response {
+    let msg = Reply {
+        id: CLAIM_REPLY_ID,
+        result: ContractResult::Ok(SubMsgExecutionResponse { events: vec![], data: None }),
+    };
+    reply(deps.as_mut(), env_at(time), msg).unwrap()
+}
+
+#[test]
+fn delegated_claim_is_paid_by_the_reward_contract_and_confirmed_by_its_reply() {
+    let (mut deps, _) = setup_with(InstantiateMsg { reward_mode: RewardMode::Delegate, ..campaign_msg() });
+    fund_rewards(&mut deps, 10_000_000);
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+
+    let claimed = claim(&mut deps, START + 100, "alice").unwrap();
+    let sub_msg = &claimed.messages[0];
+    assert_eq!(sub_msg.id, CLAIM_REPLY_ID);
+    assert_eq!(sub_msg.reply_on, ReplyOn::Success);
+    assert_eq!(sub_msg.msg, CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: "reward_contract".to_string(),
+        msg: to_binary(&RewardContractMsg::DistributeReward { recipient: "alice".to_string(), amount: Uint128::from(100_000u128) }).unwrap(),
+        funds: vec![],
+    }));
+    // nothing comes off the entry until the reward contract has paid
+    assert_eq!(entry(&deps, START + 100, "alice").claimable_reward, Uint128::from(100_000u128));
+
+    let confirmed = confirm_claim(&mut deps, START + 100);
+    assert_eq!(event_attr(&confirmed, "ipr_claim", "amount").unwrap(), "100000");
+    assert_eq!(entry(&deps, START + 100, "alice").claimable_reward, Uint128::zero());
+}
+
+#[test]
+fn delegated_claims_follow_one_another() {
+    let (mut deps, _) = setup_with(InstantiateMsg { reward_mode: RewardMode::Delegate, ..campaign_msg() });
+    fund_rewards(&mut deps, 10_000_000);
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    deposit(&mut deps, START, "bob", 1_000_000).unwrap();
+
+    claim(&mut deps, START + 100, "alice").unwrap();
+    confirm_claim(&mut deps, START + 100);
+    claim(&mut deps, START + 100, "bob").unwrap();
+    let confirmed = confirm_claim(&mut deps, START + 100);
+    assert_eq!(event_attr(&confirmed, "ipr_claim", "entry_address").unwrap(), "bob");
+    assert_eq!(stats(&deps, 0).total_reward_claimed, Uint128::from(200_000u128));
+}
+
+fn reward_budget(deps: &Deps, time: u64) -> RewardBudgetResponse {
+    fetch(deps, time, QueryMsg::GetRewardBudget { campaign_id: 0 })
+}
+
+fn set_tier(deps: &mut Deps, time: u64, tier: u8, permille: u64, tier_time: u64) {
+    run(deps, time, OWNER, ExecuteMsg::SetTierData { campaign_id: 0, data: (tier, Decimal256::permille(permille), tier_time) }).unwrap();
+}
+
+#[test]
+fn tier_rate_change_applies_from_when_it_is_made() {
+    let (mut deps, _) = setup();
+    fund_rewards(&mut deps, 10_000_000);
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    set_tier(&mut deps, START + 500, 0, 2, 0);
+
+    // 500 seconds at the old tier 0 rate and 500 at the new one
+    let claimed = claim(&mut deps, START + 1_000, "alice").unwrap();
+    assert_eq!(event_attr(&claimed, "ipr_claim", "amount").unwrap(), "1500000");
+}
+
+#[test]
+fn tier_time_change_applies_from_when_it_is_made() {
+    let (mut deps, _) = setup();
+    fund_rewards(&mut deps, 10_000_000);
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    // tier 1 now starts 200 seconds after deposit, which alice has already passed
+    set_tier(&mut deps, START + 100, 1, 2, 200);
+
+    // 100 seconds at tier 0, 100 more at tier 0 under the new schedule, then tier 1
+    let claimed = claim(&mut deps, START + 300, "alice").unwrap();
+    assert_eq!(event_attr(&claimed, "ipr_claim", "amount").unwrap(), "400000");
+}
+
+#[test]
+fn reward_rate_follows_tier_changes() {
+    let (mut deps, _) = setup();
+    fund_rewards(&mut deps, 10_000_000);
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    assert_eq!(reward_budget(&deps, START).emission_rate, Decimal256::from_uint256(1_000u64));
+
+    set_tier(&mut deps, START + 100, 0, 2, 0);
+    assert_eq!(reward_budget(&deps, START + 100).emission_rate, Decimal256::from_uint256(2_000u64));
+    // the estimate keeps what was earned at the old rate
+    assert_eq!(reward_budget(&deps, START + 200).pending_reward, Uint128::from(300_000u128));
+
+    withdraw_all(&mut deps, START + 200, "alice").unwrap();
+    assert_eq!(reward_budget(&deps, START + 200).emission_rate, Decimal256::zero());
+}
+
+#[test]
+fn throttling_stays_pro_rata_across_a_tier_change() {
+    let (mut deps, _) = setup();
+    fund_rewards(&mut deps, 225_000);
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    deposit(&mut deps, START, "bob", 1_000_000).unwrap();
+    set_tier(&mut deps, START + 50, 0, 2, 0);
+
+    // each earned 150_000 against a budget covering three quarters of it
+    let alice = claim(&mut deps, START + 100, "alice").unwrap();
+    let bob = claim(&mut deps, START + 100, "bob").unwrap();
+    assert_eq!(event_attr(&alice, "ipr_claim", "amount").unwrap(), "112500");
+    assert_eq!(event_attr(&bob, "ipr_claim", "amount").unwrap(), "112500");
+}
+
+#[test]
+fn tier_change_is_validated_and_owner_only() {
+    let (mut deps, _) = setup();
+    let msg = ExecuteMsg::SetTierData { campaign_id: 0, data: (1, Decimal256::permille(2), 5_000) };
+    assert!(matches!(run(&mut deps, START, "alice", msg.clone()).unwrap_err(), ContractError::Unauthorized {}));
+    assert!(matches!(run(&mut deps, START, OWNER, msg).unwrap_err(), ContractError::InvalidTierTimes {}));
+}
+
+// the pair prices MIN at `ust` per thousand
+fn set_pair_price(chain: &Rc<RefCell<Chain>>, ust: u128) {
+    chain.borrow_mut().pools.insert(PAIR.to_string(), (1_000, ust));
+}
+
+// a twap over 3_000 seconds, so observations are at least 100 seconds apart
+fn setup_twap(ceiling: Decimal256) -> (Deps, Rc<RefCell<Chain>>) {
+    let (mut deps, chain) = setup();
+    let msg = ExecuteMsg::SetSellbackSource {
+        campaign_id: 0,
+        source: PriceSource::Twap { pair: Addr::unchecked(PAIR), window: 3_000, max_staleness: 2_000 },
+        floor: Decimal256::zero(),
+        ceiling: Some(ceiling),
+    };
+    run(&mut deps, START, OWNER, msg).unwrap();
+    (deps, chain)
+}
+
+fn record(deps: &mut Deps, time: u64) -> Result<Response, ContractError> {
+    run(deps, time, OWNER, ExecuteMsg::RecordSellbackPrice { campaign_id: 0 })
+}
+
+fn sellback_price(deps: &Deps, time: u64) -> SellbackPriceResponse {
+    fetch(deps, time, QueryMsg::GetSellbackPrice { campaign_id: 0 })
+}
+
+#[test]
+fn pair_sources_need_a_ceiling() {
+    let (mut deps, _) = setup();
+    for source in [
+        PriceSource::Pair { pair: Addr::unchecked(PAIR) },
+        PriceSource::Twap { pair: Addr::unchecked(PAIR), window: 3_000, max_staleness: 2_000 },
+    ] {
+        let msg = ExecuteMsg::SetSellbackSource { campaign_id: 0, source, floor: Decimal256::zero(), ceiling: None };
+        assert!(matches!(run(&mut deps, START, OWNER, msg).unwrap_err(), ContractError::SellbackCeilingRequired {}));
+    }
+}
+
+#[test]
+fn only_the_owner_records_observations() {
+    let (mut deps, chain) = setup_twap(Decimal256::percent(200));
+    set_pair_price(&chain, 1_000);
+    let err = run(&mut deps, START, "alice", ExecuteMsg::RecordSellbackPrice { campaign_id: 0 }).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized {}));
+    record(&mut deps, START).unwrap();
+}
+
+#[test]
+fn observations_are_spaced_out_and_bounded() {
+    let (mut deps, chain) = setup_twap(Decimal256::percent(200));
+    set_pair_price(&chain, 1_000);
+    record(&mut deps, START).unwrap();
+    let err = record(&mut deps, START + 50).unwrap_err();
+    assert!(matches!(err, ContractError::ObservationTooSoon { time } if time == START + 100));
+
+    for sample in 1..60 {
+        record(&mut deps, START + sample * 100).unwrap();
+    }
+    let observations = PRICE_OBSERVATIONS.load(&deps.storage, 0u64.into()).unwrap();
+    assert_eq!(observations.len(), 31);
+    assert_eq!(observations[0].time, START + 2_900);
+}
+
+#[test]
+fn sparse_observations_hold_until_the_next_one() {
+    let (mut deps, chain) = setup_twap(Decimal256::percent(200));
+    set_pair_price(&chain, 1_000);
+    record(&mut deps, START).unwrap();
+    set_pair_price(&chain, 2_000);
+    record(&mut deps, START + 2_000).unwrap();
+
+    // a UST for 2_000 seconds and two for the last 1_000
+    assert_eq!(sellback_price(&deps, START + 3_000).effective_price, Decimal256::from_ratio(4u64, 3u64));
+    // nothing recorded for longer than max_staleness
+    let msg = QueryMsg::GetSellbackPrice { campaign_id: 0 };
+    assert!(query(deps.as_ref(), env_at(START + 4_001), msg).is_err());
+}
+
+#[test]
+fn a_manipulated_observation_only_weighs_its_share_and_is_capped() {
+    let (mut deps, chain) = setup_twap(Decimal256::percent(150));
+    set_pair_price(&chain, 1_000);
+    for sample in 0..30 {
+        record(&mut deps, START + sample * 100).unwrap();
+    }
+    // the pair is pushed to a hundred times its price for the last sample
+    set_pair_price(&chain, 100_000);
+    record(&mut deps, START + 3_000).unwrap();
+    assert_eq!(sellback_price(&deps, START + 3_000).effective_price, Decimal256::one());
+
+    // once it has held for 30 of the 3_000 seconds it lifts the average to 1.99, the ceiling keeps it at 1.5
+    let price = sellback_price(&deps, START + 3_030);
+    assert_eq!(price.source_price, Decimal256::from_ratio(199u64, 100u64));
+    assert_eq!(price.effective_price, Decimal256::percent(150));
+}
+
+fn create_campaign(deps: &mut Deps, msg: InstantiateMsg) -> Result<Response, ContractError> {
+    run(deps, START, OWNER, ExecuteMsg::CreateCampaign(Box::new(msg)))
+}
+
+#[test]
+fn tier_rates_have_to_climb_with_the_tiers() {
+    let (mut deps, _) = setup();
+    let err = create_campaign(&mut deps, InstantiateMsg { tier2rate: Decimal256::permille(1), ..campaign_msg() }).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidTierRates {}));
+    let err = create_campaign(&mut deps, InstantiateMsg { tier2time: 1_000, ..campaign_msg() }).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidTierTimes {}));
+    let err = create_campaign(&mut deps, InstantiateMsg { tier0time: 10, ..campaign_msg() }).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidTierTimes {}));
+    let err = run(&mut deps, START, OWNER, ExecuteMsg::SetTierData { campaign_id: 0, data: (3, Decimal256::permille(2), 3_000) }).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidTierRates {}));
+}
+
+#[test]
+fn fractional_tier_rates_round_down_once() {
+    let (mut deps, _) = setup_with(InstantiateMsg {
+        tier0rate: Decimal256::from_ratio(1u64, 3_000u64),
+        ..campaign_msg()
+    });
+    fund_rewards(&mut deps, 10_000_000);
+    deposit(&mut deps, START, "alice", 3_000_000).unwrap();
+    let claimed = claim(&mut deps, START + 1_000, "alice").unwrap();
+    // the rate is stored to 18 decimals and the reward rounded down on the whole amount
+    assert_eq!(event_attr(&claimed, "ipr_claim", "amount").unwrap(), "999999");
+}
+
+#[test]
+fn sellback_price_cannot_be_zero() {
+    let (mut deps, _) = setup();
+    let err = create_campaign(&mut deps, InstantiateMsg { sellback_price: Decimal256::zero(), ..campaign_msg() }).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidSellbackPrice {}));
+    let err = run(&mut deps, START, OWNER, ExecuteMsg::SetSellbackPrice { campaign_id: 0, price: Decimal256::zero() }).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidSellbackPrice {}));
+}
+
+#[test]
+fn sellback_band_and_twap_window_are_validated() {
+    let (mut deps, _) = setup();
+    let msg = ExecuteMsg::SetSellbackSource {
+        campaign_id: 0,
+        source: PriceSource::Static {},
+        floor: Decimal256::percent(60),
+        ceiling: Some(Decimal256::percent(50)),
+    };
+    assert!(matches!(run(&mut deps, START, OWNER, msg).unwrap_err(), ContractError::InvalidSellbackBand {}));
+    let msg = ExecuteMsg::SetSellbackSource {
+        campaign_id: 0,
+        source: PriceSource::Twap { pair: Addr::unchecked(PAIR), window: 0, max_staleness: 100 },
+        floor: Decimal256::zero(),
+        ceiling: Some(Decimal256::one()),
+    };
+    assert!(matches!(run(&mut deps, START, OWNER, msg).unwrap_err(), ContractError::InvalidTwapWindow {}));
+}
+
+#[test]
+fn static_price_is_held_within_the_band() {
+    let (mut deps, _) = setup();
+    let msg = ExecuteMsg::SetSellbackSource {
+        campaign_id: 0,
+        source: PriceSource::Static {},
+        floor: Decimal256::percent(75),
+        ceiling: None,
+    };
+    run(&mut deps, START, OWNER, msg).unwrap();
+    let price = sellback_price(&deps, START);
+    assert_eq!(price.source_price, Decimal256::percent(50));
+    assert_eq!(price.effective_price, Decimal256::percent(75));
+}
+
+fn sellback_reserve(deps: &Deps) -> SellbackReserveResponse {
+    fetch(deps, START, QueryMsg::GetSellbackReserve { campaign_id: 0 })
+}
+
+#[test]
+fn cashout_funds_the_reserve_with_its_share_of_the_yield() {
+    let (mut deps, chain) = setup_with(InstantiateMsg {
+        yield_split: Some(vec![
+            YieldShare { recipient: YieldRecipient::SellbackReserve {}, share: Decimal256::percent(25) },
+            YieldShare { recipient: YieldRecipient::Treasury {}, share: Decimal256::percent(75) },
+        ]),
+        ..campaign_msg()
+    });
+    deposit(&mut deps, START, "alice", 1_000_000).unwrap();
+    chain.borrow_mut().exchange_rate = Decimal256::percent(140);
+    let cashout = run(&mut deps, START, OWNER, ExecuteMsg::CashoutYield { campaign_id: 0 }).unwrap();
+
+    assert_eq!(event_attr(&cashout, "ipr_cashout_yield", "reserve_amount").unwrap(), "100000");
+    let reserve = sellback_reserve(&deps);
+    assert_eq!(reserve.reserve, Uint128::from(100_000u128));
+    assert_eq!(reserve.reserve_share, Decimal256::percent(25));
+    // nothing has been earned yet, so there is nothing to cover
+    assert_eq!(reserve.coverage, None);
+}
+
+#[test]
+fn sells_are_paid_out_of_the_reserve() {
+    let (mut deps, _) = setup_reserve();
+    let sold = sell(&mut deps, START, "alice", 600_000).unwrap();
+    assert_eq!(event_attr(&sold, "ipr_sell", "ust_amount").unwrap(), "300000");
+    assert!(sold.messages.iter().any(|sub_msg| sub_msg.msg == CosmosMsg::Bank(BankMsg::Send {
+        to_address: "alice".to_string(),
+        amount: coins(300_000, "uust"),
+    })));
+    assert_eq!(sellback_reserve(&deps).reserve, Uint128::from(700_000u128));
+}
+
+#[test]
+fn reserve_coverage_compares_it_to_the_outstanding_reward() {
+    let (mut deps, _) = setup_reserve();
+    fund_rewards(&mut deps, 10_000_000);
+    // alice earns 3_000_000 MIN, worth 1_500_000 UST at the sellback price, and sells a third of it
+    claim(&mut deps, START + 2_000, "alice").unwrap();
+    sell(&mut deps, START + 2_000, "alice", 1_000_000).unwrap();
+    let reserve = sellback_reserve(&deps);
+    assert_eq!(reserve.outstanding_reward, Uint128::from(2_000_000u128));
+    assert_eq!(reserve.liability, Uint128::from(1_000_000u128));
+    assert_eq!(reserve.coverage, Some(Decimal256::percent(50)));
+}
+
+#[test]
+fn queued_sell_is_handed_back_in_the_token_it_was_taken_in() {
+    let (mut deps, _) = setup_reserve();
+    run(&mut deps, START, OWNER, ExecuteMsg::SetSellLimits { campaign_id: 0, epoch_length: 1_000, global_cap: None, address_cap: Some(Uint128::zero()) }).unwrap();
+    queue_sell(&mut deps, START, "alice", 100).unwrap();
+    run(&mut deps, START, OWNER, ExecuteMsg::SetRewardToken { campaign_id: 0, address: "new_token".to_string() }).unwrap();
+
+    let cancelled = run(&mut deps, START, "alice", ExecuteMsg::CancelQueuedSell { campaign_id: 0, id: 0 }).unwrap();
+    let refund = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: REWARD_TOKEN.to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Transfer { recipient: "alice".to_string(), amount: Uint128::from(100u128) }).unwrap(),
+        funds: vec![],
+    });
+    assert_eq!(cancelled.messages[0].msg, refund);
+    assert_eq!(event_attr(&cancelled, "ipr_sell_cancelled", "reward_token").unwrap(), REWARD_TOKEN);
+}
+
+#[test]
+fn every_event_names_its_campaign() {
+    let (mut deps, chain) = setup();
+    run(&mut deps, START, OWNER, ExecuteMsg::CreateCampaign(Box::new(campaign_msg()))).unwrap();
+    let mut responses = vec![
+        run(&mut deps, START, OWNER, ExecuteMsg::FundRewards { campaign_id: 1, amount: Uint128::from(10_000_000u128) }).unwrap(),
+        run(&mut deps, START, OWNER, ExecuteMsg::SetReferralTerms { campaign_id: 1, share: Decimal256::percent(10), period: 10_000 }).unwrap(),
+        run(&mut deps, START, OWNER, ExecuteMsg::SetStakeTerms { campaign_id: 1, boost_rate: Decimal256::one(), boost_cap: Decimal256::one(), unstake_cooldown: 100 }).unwrap(),
+        run(&mut deps, START, OWNER, ExecuteMsg::SetSellLimits { campaign_id: 1, epoch_length: 1_000, global_cap: None, address_cap: Some(Uint128::zero()) }).unwrap(),
+    ];
+    let msg = ExecuteMsg::DepositUst { campaign_id: 1, entry_address: "alice".to_string(), amount: Uint128::from(1_000_000u128), referrer: Some("carol".to_string()) };
+    responses.push(execute(deps.as_mut(), env_at(START), mock_info("alice", &coins(1_000_000, "uust")), msg).unwrap());
+    responses.push(run(&mut deps, START + 1_000, "alice", ExecuteMsg::StakeReward { campaign_id: 1, entry_address: "alice".to_string(), amount: Uint128::from(1_000u128) }).unwrap());
+    responses.push(run(&mut deps, START + 1_000, "alice", ExecuteMsg::UnstakeReward { campaign_id: 1, entry_address: "alice".to_string(), amount: Uint128::from(500u128) }).unwrap());
+    responses.push(run(&mut deps, START + 1_000, "alice", ExecuteMsg::SetAutoCompound { campaign_id: 1, entry_address: "alice".to_string(), enabled: true }).unwrap());
+    responses.push(run(&mut deps, START + 1_000, "alice", ExecuteMsg::TransferEntry { campaign_id: 1, to: "bob".to_string(), amount: Uint128::from(100u128) }).unwrap());
+    // a claim past the first tier boundary promotes the tranche and pays carol's bonus
+    responses.push(run(&mut deps, START + 2_000, "alice", ExecuteMsg::ClaimReward { campaign_id: 1, entry_address: "alice".to_string() }).unwrap());
+    let sell = ExecuteMsg::SellReward { campaign_id: 1, entry_address: "alice".to_string(), amount: Uint128::from(100u128), on_excess: Some(SellExcess::Queue) };
+    responses.push(run(&mut deps, START + 2_000, "alice", sell).unwrap());
+    responses.push(run(&mut deps, START + 2_000, "alice", ExecuteMsg::CancelQueuedSell { campaign_id: 1, id: 0 }).unwrap());
+    let source = ExecuteMsg::SetSellbackSource {
+        campaign_id: 1,
+        source: PriceSource::Twap { pair: Addr::unchecked(PAIR), window: 3_000, max_staleness: 2_000 },
+        floor: Decimal256::zero(),
+        ceiling: Some(Decimal256::one()),
+    };
+    responses.push(run(&mut deps, START + 2_000, OWNER, source).unwrap());
+    set_pair_price(&chain, 500);
+    responses.push(run(&mut deps, START + 2_000, OWNER, ExecuteMsg::RecordSellbackPrice { campaign_id: 1 }).unwrap());
+
+    let events: Vec<_> = responses.iter().flat_map(|response| response.events.iter()).collect();
+    for ty in ["ipr_config_changed", "ipr_fund_rewards", "ipr_referral", "ipr_deposit", "ipr_tier_promoted", "ipr_referral_reward", "ipr_claim", "ipr_stake", "ipr_unstake", "ipr_auto_compound", "ipr_position_transfer", "ipr_sell_queued", "ipr_sell_cancelled", "ipr_sellback_price_recorded"].iter() {
+        assert!(events.iter().any(|event| event.ty == *ty), "no {} event", ty);
+    }
+    for event in events {
+        let campaign_id = event.attributes.iter().find(|attribute| attribute.key == "campaign_id");
+        assert_eq!(campaign_id.map(|attribute| attribute.value.as_str()), Some("1"), "{} event", event.ty);
     }
 }
-*/